@@ -0,0 +1,47 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+    thread,
+};
+
+use log::{error, info};
+
+use crate::state::{alert, environment};
+
+/// Run `alert`'s hook through `shell` on a new thread, feeding the run's stdout to its stdin.
+pub fn run(shell: &str, environment: &environment::Environment, alert: alert::Alert) {
+    let mut process = Command::new(shell);
+    process
+        .arg("-c")
+        .arg(&alert.hook)
+        .envs(environment.vars.iter().map(|(k, v)| (k, v)))
+        .envs(alert.vars)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if let Some(cwd) = &environment.cwd {
+        process.current_dir(cwd);
+    }
+
+    let event = alert.event.name();
+    let stdin = alert.stdin;
+    thread::spawn(move || {
+        let mut child = match process.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                error!("error spawn {} hook: {}", event, e);
+                return;
+            }
+        };
+        if let Some(mut pipe) = child.stdin.take() {
+            // NOTE: a hook is free to ignore its stdin
+            let _ = pipe.write_all(&stdin);
+        }
+
+        match child.wait() {
+            Ok(status) if status.success() => info!("ran {} hook", event),
+            Ok(status) => error!("{} hook exited with {}", event, status),
+            Err(e) => error!("error wait {} hook: {}", event, e),
+        }
+    });
+}
@@ -1,73 +1,205 @@
 use std::{
-    env,
-    process::Command,
+    fs,
+    io::{self, Read, Write},
+    os::unix::process::ExitStatusExt,
+    path::Path,
+    process::{Child, Command, ExitStatus, Stdio},
     sync::{Arc, RwLock},
     thread::{self, JoinHandle},
-    time::Instant,
+    time::{Duration, Instant},
 };
 
-use crossbeam_channel::{select, tick, unbounded};
+use crossbeam_channel::{bounded, never, select, tick, unbounded};
 use log::{debug, error};
 
-use crate::state::{action, state};
+use crate::state::{action, blob, environment, state};
+
+use super::trigger;
+
+/// Output of a finished process, truncated to `--max-output` if set.
+struct Captured {
+    status: ExitStatus,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    stdout_size: u64,
+    stderr_size: u64,
+    usage: action::Usage,
+}
 
 pub struct Manager {
     command_tx: crossbeam_channel::Sender<action::Command>,
-
-    shell: String,
 }
 
 impl Manager {
     pub fn new() -> (Manager, crossbeam_channel::Receiver<action::Command>) {
-        let shell = match env::var("SHELL") {
-            Ok(s) => s,
-            Err(_) => "/bin/sh".to_string(),
+        let (tx, rx) = unbounded::<action::Command>();
+        (Manager { command_tx: tx }, rx)
+    }
+
+    fn build(runner: &state::Runner, command: &[String]) -> Command {
+        match runner {
+            state::Runner::Shell(shell) => {
+                let mut process = Command::new(shell);
+                process.arg("-c").arg(command.join(" "));
+                process
+            }
+            state::Runner::Exec => {
+                let mut process = Command::new(command.first().map_or("", String::as_str));
+                process.args(command.iter().skip(1));
+                process
+            }
+        }
+    }
+
+    /// Spawn `process` with stdin fed from `stdin`, or null when unset, and wait for it.
+    /// Output is handed to `on_chunk` as it arrives, and returned whole once the process exits.
+    fn output(
+        mut process: Command,
+        stdin: Option<&environment::Stdin>,
+        max_output: Option<u64>,
+        on_chunk: impl Fn(action::Stream, Vec<u8>) + Clone + Send + 'static,
+    ) -> io::Result<Captured> {
+        let input = stdin.map(|stdin| stdin.read()).transpose()?;
+
+        let mut child = process
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        if let (Some(input), Some(mut pipe)) = (input, child.stdin.take()) {
+            // NOTE: write from another thread so a command that does not drain stdin cannot block us
+            thread::spawn(move || {
+                if let Err(e) = pipe.write_all(&input) {
+                    debug!("error write stdin: {}", e);
+                }
+            });
+        }
+
+        let stdout = child
+            .stdout
+            .take()
+            .map(|pipe| read_stream(pipe, action::Stream::Stdout, max_output, on_chunk.clone()));
+        let stderr = child
+            .stderr
+            .take()
+            .map(|pipe| read_stream(pipe, action::Stream::Stderr, max_output, on_chunk));
+
+        let (status, usage) = wait(&child)?;
+        let join = |handle: Option<JoinHandle<(Vec<u8>, u64)>>| {
+            handle
+                .map(|handle| handle.join().unwrap_or_default())
+                .unwrap_or_default()
         };
+        let (stdout, stdout_size) = join(stdout);
+        let (stderr, stderr_size) = join(stderr);
 
-        let (tx, rx) = unbounded::<action::Command>();
-        (
-            Manager {
-                command_tx: tx,
-
-                shell,
-            },
-            rx,
-        )
+        Ok(Captured {
+            status,
+            stdout,
+            stderr,
+            stdout_size,
+            stderr_size,
+            usage,
+        })
     }
 
-    pub fn execute(&self, t: Instant, state: &Arc<RwLock<state::State>>) {
+    pub fn execute(&self, watch: usize, t: Instant, state: &Arc<RwLock<state::State>>) {
         debug!("run!");
-        let shell = self.shell.clone();
         let command_tx = self.command_tx.clone();
-        let command = {
+        let (runner, environment, max_output, compress, prev_output, command) = {
             let state = state.read().unwrap();
-            state.global.command.clone()
+            (
+                state.global.runner.clone(),
+                state.global.environment.clone(),
+                state.global.max_output,
+                state.global.compress,
+                state.global.prev_output_path(watch),
+                state.global.watches[watch].command.clone(),
+            )
         };
 
         thread::spawn(move || {
             let start = chrono::Local::now();
+            let (started_tx, started_rx) = bounded::<action::Started>(1);
             command_tx
-                .send(action::Command::StartRun(t, start))
+                .send(action::Command::StartRun(watch, t, start, started_tx))
                 .unwrap();
+            let started = match started_rx.recv() {
+                Ok(started) => started,
+                Err(e) => {
+                    error!("error receive started: {}", e);
+                    return;
+                }
+            };
 
-            let command = command.join(" ");
-            let result = Command::new(shell)
-                .args(["-c"])
-                .arg(command)
-                .output()
-                .unwrap();
+            let mut process = Manager::build(&runner, &command);
+            if let Some(cwd) = &environment.cwd {
+                process.current_dir(cwd);
+            }
+            process
+                .envs(environment.vars.iter().map(|(k, v)| (k, v)))
+                .env("BODA_RUN_ID", started.id.to_string())
+                .env("BODA_ITERATION", started.iteration.to_string());
+            if let Some(prev_status) = started.prev_status {
+                process.env("BODA_PREV_STATUS", prev_status.to_string());
+            }
+            if let Some(prev_output) = &started.prev_output {
+                process.env("BODA_PREV_OUTPUT_FILE", prev_output);
+            }
+
+            let chunk_tx = command_tx.clone();
+            let on_chunk = move |stream, chunk| {
+                if let Err(e) = chunk_tx.send(action::Command::Chunk(watch, start, stream, chunk)) {
+                    error!("error send chunk: {}", e);
+                }
+            };
 
+            let (mut output, status) =
+                match Manager::output(process, environment.stdin.as_ref(), max_output, on_chunk) {
+                    Ok(captured) => (
+                        action::RunOutput {
+                            stdout: captured.stdout,
+                            stderr: captured.stderr,
+                            stdout_size: captured.stdout_size,
+                            stderr_size: captured.stderr_size,
+                            usage: Some(captured.usage),
+                            signal: captured.status.signal().map(|signal| signal as u8),
+                            blobs: None,
+                        },
+                        exit_code(captured.status),
+                    ),
+                    Err(e) => {
+                        error!("error spawn command: {}", e);
+                        let stderr = format!("{}: {}", command.join(" "), e).into_bytes();
+                        // NOTE: mirror the shell's "command not found" status
+                        (
+                            action::RunOutput {
+                                stderr_size: stderr.len() as u64,
+                                stderr,
+                                ..action::RunOutput::default()
+                            },
+                            127,
+                        )
+                    }
+                };
             let end = chrono::Local::now();
-            let stdout = String::from_utf8_lossy(&result.stdout).to_string();
-            let stderr = String::from_utf8_lossy(&result.stderr).to_string();
-            let status = result.status;
+            write_prev_output(&prev_output, started.id, &output.stdout);
+            output.blobs = Some((
+                blob::Blob::new(&output.stdout, compress),
+                blob::Blob::new(&output.stderr, compress),
+            ));
 
             if let Err(e) = command_tx.send(action::Command::RunResult(
+                watch,
                 start,
                 end,
-                stdout,
-                stderr,
-                status.code().unwrap() as u8,
+                Box::new(output),
+                status,
             )) {
                 error!("error send command result: {}", e);
             }
@@ -78,16 +210,32 @@ impl Manager {
 
     pub fn run(self, state: Arc<RwLock<state::State>>) -> JoinHandle<()> {
         thread::spawn(move || {
-            let tick_duration = {
+            let (tick_duration, watches, mut changed_rx) = {
                 let state = state.read().unwrap();
-                state.command.tick
+                let changed_rx = if state.global.watch_paths.is_empty() {
+                    never()
+                } else {
+                    trigger::spawn(state.global.watch_paths.clone(), state.global.debounce)
+                };
+                (state.command.tick, state.global.watches.len(), changed_rx)
             };
             let ticker = tick(tick_duration);
             // NOTE: Run at first
-            self.execute(Instant::now(), &state);
+            for watch in 0..watches {
+                self.execute(watch, Instant::now(), &state);
+            }
 
             loop {
                 select! {
+                    recv(changed_rx) -> paths => match paths {
+                        Ok(paths) => {
+                            if let Err(e) = self.command_tx.send(action::Command::PathsChanged(paths)) {
+                                error!("error send changed paths: {}", e);
+                            }
+                        }
+                        // NOTE: the watcher stopped, keep running on the interval alone
+                        Err(_) => changed_rx = never(),
+                    },
                     recv(ticker) -> ticker_recv => {
                         if let Ok(t) = ticker_recv {
                             {
@@ -98,13 +246,15 @@ impl Manager {
                             }
 
 
-                            let can_run = {
-                                let state = state.read().unwrap();
-                                state.can_run(t)
-                            };
+                            for watch in 0..watches {
+                                let can_run = {
+                                    let state = state.read().unwrap();
+                                    state.can_run(watch, t)
+                                };
 
-                            if can_run {
-                                self.execute(t, &state);
+                                if can_run {
+                                    self.execute(watch, t, &state);
+                                }
                             }
                         }
                     }
@@ -113,3 +263,90 @@ impl Manager {
         })
     }
 }
+
+/// Reap `child` with `wait4`, which also reports the usage of descendants it waited for.
+fn wait(child: &Child) -> io::Result<(ExitStatus, action::Usage)> {
+    let mut status = 0;
+    // SAFETY: `rusage` is plain data, and `wait4` only writes into the pointers it is given
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        let pid = unsafe { libc::wait4(child.id() as libc::pid_t, &mut status, 0, &mut rusage) };
+        if pid >= 0 {
+            break;
+        }
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+
+    let time = |tv: libc::timeval| {
+        Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+    };
+    // NOTE: Linux reports `ru_maxrss` in KiB and block I/O in 512-byte units
+    let usage = action::Usage {
+        user_time: time(rusage.ru_utime),
+        system_time: time(rusage.ru_stime),
+        max_rss: rusage.ru_maxrss as u64 * 1024,
+        read_bytes: rusage.ru_inblock as u64 * 512,
+        write_bytes: rusage.ru_oublock as u64 * 512,
+    };
+
+    Ok((ExitStatus::from_raw(status), usage))
+}
+
+/// Keep the latest stdout of a watch on disk for `BODA_PREV_OUTPUT_FILE`.
+fn write_prev_output(path: &Path, id: i64, stdout: &[u8]) {
+    // NOTE: rename so a running command never reads a partial file, runs of a watch may overlap
+    let tmp = path.with_extension(format!("{}.tmp", id));
+    if let Err(e) = fs::write(&tmp, stdout).and_then(|_| fs::rename(&tmp, path)) {
+        error!("error on write previous output: {}", e);
+    }
+}
+
+/// Exit code as a shell would report it, `128 + n` when killed by signal `n`.
+fn exit_code(status: ExitStatus) -> u8 {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => code as u8,
+        (None, Some(signal)) => (128 + signal) as u8,
+        (None, None) => u8::MAX,
+    }
+}
+
+/// Read `pipe` until EOF on a new thread, handing each raw chunk to `on_chunk`.
+/// Bytes past `max_output` are drained but dropped; the total size is returned with the kept output.
+fn read_stream<R: Read + Send + 'static>(
+    mut pipe: R,
+    stream: action::Stream,
+    max_output: Option<u64>,
+    on_chunk: impl Fn(action::Stream, Vec<u8>) + Send + 'static,
+) -> JoinHandle<(Vec<u8>, u64)> {
+    thread::spawn(move || {
+        let limit = max_output.map_or(usize::MAX, |it| it as usize);
+        let mut output = vec![];
+        let mut size = 0u64;
+        let mut buf = [0u8; 8192];
+
+        loop {
+            match pipe.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    size += n as u64;
+                    let keep = n.min(limit.saturating_sub(output.len()));
+                    if keep == 0 {
+                        continue;
+                    }
+                    output.extend_from_slice(&buf[..keep]);
+                    on_chunk(stream, buf[..keep].to_vec());
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    error!("error read output: {}", e);
+                    break;
+                }
+            }
+        }
+
+        (output, size)
+    })
+}
@@ -1 +1,4 @@
+pub mod hook;
 pub mod manager;
+pub mod trigger;
+pub mod webhook;
@@ -0,0 +1,399 @@
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    fs::{self, File},
+    io::{self, Read},
+    os::{
+        fd::{AsRawFd, FromRawFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, never, unbounded};
+use log::{debug, error, info, warn};
+use regex::Regex;
+
+const MASK: u32 = libc::IN_CLOSE_WRITE
+    | libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO;
+// NOTE: `wd`, `mask`, `cookie` and `len` precede the name of every event
+const EVENT_HEADER: usize = 16;
+
+/// A `--watch-path` glob, e.g. `src/**/*.rs`, `*.{c,h}`, `config.toml`.
+///
+/// `*` and `?` stay within a path component, `**` spans any number of them.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    /// Directory above the first wildcard, watched for changes.
+    base: PathBuf,
+    /// How many directories below `base` a match can be, unlimited with `**`.
+    depth: Option<usize>,
+    regex: Regex,
+}
+
+impl Pattern {
+    pub fn parse(input: &str) -> Result<Pattern, String> {
+        let glob = input.trim().trim_start_matches("./");
+        if glob.is_empty() {
+            return Err("empty path".to_string());
+        }
+
+        let components = glob.split('/').collect::<Vec<_>>();
+        let literal = components
+            .iter()
+            .position(|component| component.contains(['*', '?', '[', '{']))
+            .unwrap_or(components.len());
+        let (base, rest, regex) = if literal == components.len() {
+            // NOTE: a plain directory matches everything below it, a plain file only itself
+            let path = Path::new(glob);
+            if path.is_dir() {
+                (
+                    path.to_path_buf(),
+                    "**".to_string(),
+                    format!("{}(/.*)?", regex::escape(glob)),
+                )
+            } else {
+                let base = path.parent().unwrap_or(Path::new("")).to_path_buf();
+                (base, String::new(), regex::escape(glob))
+            }
+        } else {
+            let base = components[..literal].join("/");
+            let base = if base.is_empty() && glob.starts_with('/') {
+                "/".to_string()
+            } else {
+                base
+            };
+            (
+                PathBuf::from(base),
+                components[literal..].join("/"),
+                translate(glob)?,
+            )
+        };
+
+        let depth = if rest.contains("**") {
+            None
+        } else {
+            Some(rest.matches('/').count())
+        };
+        let regex = Regex::new(&format!("^{}$", regex)).map_err(|e| e.to_string())?;
+        Ok(Pattern { base, depth, regex })
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        path.to_str().is_some_and(|path| self.regex.is_match(path))
+    }
+}
+
+/// Glob to regex, `**/` also matching no directory at all.
+fn translate(glob: &str) -> Result<String, String> {
+    let mut regex = String::new();
+    let mut chars = glob.chars().peekable();
+    let mut braces = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                regex.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    regex.push('^');
+                }
+                loop {
+                    match chars.next() {
+                        Some(']') => break,
+                        Some('\\') => regex.push_str("\\\\"),
+                        Some(c) => regex.push(c),
+                        None => return Err(format!("unclosed [ in {glob}")),
+                    }
+                }
+                regex.push(']');
+            }
+            '{' => {
+                braces += 1;
+                regex.push_str("(?:");
+            }
+            ',' if braces > 0 => regex.push('|'),
+            '}' if braces > 0 => {
+                braces -= 1;
+                regex.push(')');
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    if braces > 0 {
+        return Err(format!("unclosed {{ in {glob}"));
+    }
+    Ok(regex)
+}
+
+/// Inotify watches on the directories `--watch-path` patterns can match in.
+struct Watcher {
+    file: File,
+    patterns: Vec<Pattern>,
+    /// Directory of every watch descriptor, spelled as in the pattern.
+    dirs: HashMap<i32, PathBuf>,
+}
+
+impl Watcher {
+    fn new(patterns: Vec<Pattern>) -> io::Result<Watcher> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: `fd` was just opened and is owned by `file` from here on
+        let file = unsafe { File::from_raw_fd(fd) };
+        let mut watcher = Watcher {
+            file,
+            patterns,
+            dirs: HashMap::new(),
+        };
+
+        for index in 0..watcher.patterns.len() {
+            let Pattern { base, depth, .. } = watcher.patterns[index].clone();
+            if let Err(e) = watcher.add(&base, depth) {
+                warn!("cannot watch {:?}: {}", base, e);
+            }
+        }
+        Ok(watcher)
+    }
+
+    /// Watch `dir` and its subdirectories down to `depth`, hidden ones excepted.
+    fn add(&mut self, dir: &Path, depth: Option<usize>) -> io::Result<()> {
+        let target = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let path = CString::new(target.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let wd = unsafe { libc::inotify_add_watch(self.file.as_raw_fd(), path.as_ptr(), MASK) };
+        if wd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        self.dirs.entry(wd).or_insert_with(|| dir.to_path_buf());
+        debug!("watching {:?}", target);
+
+        if depth == Some(0) {
+            return Ok(());
+        }
+        for entry in fs::read_dir(target)?.flatten() {
+            let name = entry.file_name();
+            if entry.file_type().is_ok_and(|t| t.is_dir())
+                && !name.as_bytes().starts_with(b".")
+                && let Err(e) = self.add(&dir.join(name), depth.map(|depth| depth - 1))
+            {
+                debug!("cannot watch {:?}: {}", entry.path(), e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Block for the next batch of events, returning the changed paths matching a pattern.
+    fn read(&mut self) -> io::Result<Vec<PathBuf>> {
+        let mut buf = [0u8; 4096];
+        let n = self.file.read(&mut buf)?;
+
+        let mut paths = vec![];
+        let mut offset = 0;
+        while offset + EVENT_HEADER <= n {
+            let field = |at: usize| buf[offset + at..offset + at + 4].try_into().unwrap();
+            let wd = i32::from_ne_bytes(field(0));
+            let mask = u32::from_ne_bytes(field(4));
+            let len = u32::from_ne_bytes(field(12)) as usize;
+            let name = &buf[offset + EVENT_HEADER..offset + EVENT_HEADER + len];
+            let name = &name[..name.iter().position(|b| *b == 0).unwrap_or(len)];
+            offset += EVENT_HEADER + len;
+
+            if mask & libc::IN_Q_OVERFLOW != 0 {
+                // NOTE: events were dropped, anything may have changed
+                warn!("inotify queue overflowed");
+                paths.push(PathBuf::new());
+                continue;
+            }
+            if mask & libc::IN_IGNORED != 0 {
+                self.dirs.remove(&wd);
+                continue;
+            }
+            let Some(dir) = self.dirs.get(&wd) else {
+                continue;
+            };
+            let path = dir.join(std::ffi::OsStr::from_bytes(name));
+
+            if mask & libc::IN_ISDIR != 0
+                && mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0
+                && !name.starts_with(b".")
+            {
+                // NOTE: `usize::MAX` stands for unlimited so it wins over any depth
+                let depth = self
+                    .patterns
+                    .iter()
+                    .filter_map(|pattern| {
+                        let below = path.strip_prefix(&pattern.base).ok()?;
+                        match pattern.depth {
+                            None => Some(usize::MAX),
+                            Some(depth) => depth.checked_sub(below.components().count()),
+                        }
+                    })
+                    .max();
+                if let Some(depth) = depth
+                    && let Err(e) = self.add(&path, Some(depth).filter(|d| *d != usize::MAX))
+                {
+                    debug!("cannot watch {:?}: {}", path, e);
+                }
+            }
+            if self.patterns.iter().any(|pattern| pattern.matches(&path)) {
+                paths.push(path);
+            }
+        }
+        Ok(paths)
+    }
+}
+
+/// Changed paths matching `patterns`, received once no matching change happened for `debounce`.
+pub fn spawn(patterns: Vec<Pattern>, debounce: Duration) -> Receiver<Vec<PathBuf>> {
+    let mut watcher = match Watcher::new(patterns) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            error!("cannot watch paths: {}", e);
+            return never();
+        }
+    };
+    info!("watching {} directories", watcher.dirs.len());
+
+    let (tx, rx) = unbounded::<Vec<PathBuf>>();
+    let (changed_tx, changed_rx) = unbounded::<Vec<PathBuf>>();
+    thread::spawn(move || {
+        loop {
+            match watcher.read() {
+                Ok(paths) if paths.is_empty() => {}
+                Ok(paths) => {
+                    if tx.send(paths).is_err() {
+                        break;
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    error!("error read inotify: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+
+    thread::spawn(move || {
+        while let Ok(mut changed) = rx.recv() {
+            loop {
+                match rx.recv_timeout(debounce) {
+                    Ok(paths) => changed.extend(paths),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            changed.sort();
+            changed.dedup();
+            if changed_tx.send(changed).is_err() {
+                break;
+            }
+        }
+    });
+
+    changed_rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(glob: &str) -> (Pattern, PathBuf, Option<usize>) {
+        let pattern = Pattern::parse(glob).unwrap();
+        let (base, depth) = (pattern.base.clone(), pattern.depth);
+        (pattern, base, depth)
+    }
+
+    #[test]
+    fn translates_globs() {
+        assert_eq!(translate("src/**/*.rs").unwrap(), r"src/(.*/)?[^/]*\.rs");
+        assert_eq!(translate("*.{c,h}").unwrap(), r"[^/]*\.(?:c|h)");
+        assert_eq!(translate("[!a]*").unwrap(), "[^a][^/]*");
+        assert_eq!(translate("/tmp/*.rs").unwrap(), r"/tmp/[^/]*\.rs");
+        assert!(translate("[ab").is_err());
+        assert!(translate("{a,b").is_err());
+    }
+
+    #[test]
+    fn parses_recursive_glob() {
+        let (pattern, base, depth) = parse("src/**/*.rs");
+        assert_eq!(base, Path::new("src"));
+        assert_eq!(depth, None);
+        assert!(pattern.matches(Path::new("src/main.rs")));
+        assert!(pattern.matches(Path::new("src/command/trigger.rs")));
+        assert!(!pattern.matches(Path::new("src/main.c")));
+        assert!(!pattern.matches(Path::new("main.rs")));
+    }
+
+    #[test]
+    fn parses_alternatives() {
+        let (pattern, base, depth) = parse("*.{c,h}");
+        assert_eq!(base, Path::new(""));
+        assert_eq!(depth, Some(0));
+        assert!(pattern.matches(Path::new("main.c")));
+        assert!(pattern.matches(Path::new("main.h")));
+        assert!(!pattern.matches(Path::new("main.o")));
+        assert!(!pattern.matches(Path::new("src/main.c")));
+    }
+
+    #[test]
+    fn parses_negated_class() {
+        let (pattern, base, depth) = parse("[!a]*");
+        assert_eq!(base, Path::new(""));
+        assert_eq!(depth, Some(0));
+        assert!(pattern.matches(Path::new("build")));
+        assert!(!pattern.matches(Path::new("abc")));
+        assert!(!pattern.matches(Path::new("b/c")));
+    }
+
+    #[test]
+    fn parses_absolute_glob() {
+        let (pattern, base, depth) = parse("/tmp/*.rs");
+        assert_eq!(base, Path::new("/tmp"));
+        assert_eq!(depth, Some(0));
+        assert!(pattern.matches(Path::new("/tmp/main.rs")));
+        assert!(!pattern.matches(Path::new("/tmp/src/main.rs")));
+        assert!(!pattern.matches(Path::new("tmp/main.rs")));
+    }
+
+    // NOTE: plain paths are told apart on disk, tests run from the crate root
+    #[test]
+    fn parses_plain_file() {
+        let (pattern, base, depth) = parse("Cargo.toml");
+        assert_eq!(base, Path::new(""));
+        assert_eq!(depth, Some(0));
+        assert!(pattern.matches(Path::new("Cargo.toml")));
+        assert!(!pattern.matches(Path::new("Cargo.toml.bak")));
+    }
+
+    #[test]
+    fn parses_plain_directory() {
+        let (pattern, base, depth) = parse("./src");
+        assert_eq!(base, Path::new("src"));
+        assert_eq!(depth, None);
+        assert!(pattern.matches(Path::new("src")));
+        assert!(pattern.matches(Path::new("src/command/trigger.rs")));
+        assert!(!pattern.matches(Path::new("srcs/main.rs")));
+    }
+}
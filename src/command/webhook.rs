@@ -0,0 +1,228 @@
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{RecvTimeoutError, unbounded};
+use log::{error, info, warn};
+
+use crate::{
+    state::{alert, state::CommandResult},
+    util,
+};
+
+const MAX_ATTEMPTS: u32 = 5;
+const BACKOFF: Duration = Duration::from_secs(1);
+const TIMEOUT: Duration = Duration::from_secs(10);
+const EXCERPT_LINES: usize = 20;
+
+/// POSTs JSON payloads to `--webhook` from its own thread, retrying failures with exponential backoff.
+#[derive(Debug)]
+pub struct Webhook {
+    pub events: Vec<alert::Event>,
+    tx: crossbeam_channel::Sender<Run>,
+}
+
+/// A finished run firing `events`, turned into payloads on the webhook thread.
+#[derive(Debug)]
+pub struct Run {
+    pub events: Vec<alert::Event>,
+    pub status: u8,
+    pub result: Option<Arc<CommandResult>>,
+    /// Result of the previously completed run, diffed against this one.
+    pub prev_result: Option<Arc<CommandResult>>,
+    /// Fields shared by the payload of every event.
+    pub metadata: serde_json::Value,
+}
+
+impl Run {
+    /// One payload per event, with the tail of the shown output and a diff of stdout.
+    fn payloads(&self, encoding: &util::encoding::Encoding) -> Vec<serde_json::Value> {
+        let decode = |output: Option<&Vec<u8>>| {
+            output.map_or(String::new(), |output| encoding.decode(output))
+        };
+        let result = self.result.as_deref();
+        let stdout = decode(result.and_then(|result| result.stdout.as_ref()));
+        let shown = if self.status == 0 {
+            stdout.clone()
+        } else {
+            decode(result.and_then(|result| result.stderr.as_ref()))
+        };
+        let lines = shown.lines().collect::<Vec<_>>();
+        let excerpt = lines[lines.len().saturating_sub(EXCERPT_LINES)..].join("\n");
+        let diff = self
+            .prev_result
+            .as_ref()
+            .and_then(|prev| prev.stdout.as_ref())
+            .map(|prev| {
+                let (added, removed) = alert::diff_summary(&encoding.decode(prev), &stdout);
+                serde_json::json!({ "added": added, "removed": removed })
+            });
+
+        self.events
+            .iter()
+            .map(|event| {
+                let mut payload = self.metadata.clone();
+                payload["event"] = event.name().into();
+                payload["output_excerpt"] = excerpt.clone().into();
+                payload["diff"] = diff.clone().into();
+                payload
+            })
+            .collect()
+    }
+}
+
+struct Pending {
+    payload: serde_json::Value,
+    attempts: u32,
+    due: Instant,
+}
+
+impl Webhook {
+    pub fn spawn(
+        url: String,
+        events: Vec<alert::Event>,
+        encoding: util::encoding::Encoding,
+    ) -> Webhook {
+        let (tx, rx) = unbounded::<Run>();
+        thread::spawn(move || run(&url, rx, &encoding, BACKOFF));
+        Webhook { events, tx }
+    }
+
+    pub fn send(&self, run: Run) {
+        if let Err(e) = self.tx.send(run) {
+            error!("error queue webhook: {}", e);
+        }
+    }
+}
+
+/// Deliver payloads of runs from `rx`, waiting `backoff`, then twice as long each time, between attempts.
+fn run(
+    url: &str,
+    rx: crossbeam_channel::Receiver<Run>,
+    encoding: &util::encoding::Encoding,
+    backoff: Duration,
+) {
+    let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+    let mut queue = VecDeque::<Pending>::new();
+
+    loop {
+        let received = match queue.iter().map(|pending| pending.due).min() {
+            Some(due) => rx.recv_timeout(due.saturating_duration_since(Instant::now())),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(run) => queue.extend(run.payloads(encoding).into_iter().map(|payload| Pending {
+                payload,
+                attempts: 0,
+                due: Instant::now(),
+            })),
+            Err(RecvTimeoutError::Timeout) => {}
+            // NOTE: boda is exiting, anything still queued is dropped
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let now = Instant::now();
+        for _ in 0..queue.len() {
+            let Some(mut pending) = queue.pop_front() else {
+                break;
+            };
+            if pending.due > now {
+                queue.push_back(pending);
+                continue;
+            }
+
+            match agent
+                .post(url)
+                .set("Content-Type", "application/json")
+                .send_string(&pending.payload.to_string())
+            {
+                Ok(_) => info!("sent webhook"),
+                Err(e) => {
+                    pending.attempts += 1;
+                    if pending.attempts >= MAX_ATTEMPTS {
+                        error!(
+                            "giving up webhook after {} attempts: {}",
+                            pending.attempts, e
+                        );
+                        continue;
+                    }
+                    let backoff = backoff * 2u32.pow(pending.attempts - 1);
+                    warn!("error send webhook, retrying in {:?}: {}", backoff, e);
+                    pending.due = Instant::now() + backoff;
+                    queue.push_back(pending);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tiny_http::{Response, Server};
+
+    use super::*;
+
+    fn result(id: i64, stdout: &str) -> Arc<CommandResult> {
+        Arc::new(CommandResult {
+            id,
+            start: chrono::Local::now(),
+            stdout: Some(stdout.as_bytes().to_vec()),
+            stderr: Some(vec![]),
+            stdout_size: Some(stdout.len() as u64),
+            stderr_size: Some(0),
+            status: Some(0),
+        })
+    }
+
+    #[test]
+    fn retries_until_accepted() {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", server.server_addr().to_ip().unwrap());
+        let (tx, rx) = unbounded();
+        thread::spawn(move || {
+            run(
+                &url,
+                rx,
+                &util::encoding::Encoding::default(),
+                Duration::from_millis(10),
+            )
+        });
+        tx.send(Run {
+            events: vec![alert::Event::Change],
+            status: 0,
+            result: Some(result(2, "b\nc\nd\n")),
+            prev_result: Some(result(1, "a\nb\n")),
+            metadata: json!({ "run_id": 2 }),
+        })
+        .unwrap();
+
+        for status in [500, 200] {
+            let mut request = server
+                .recv_timeout(Duration::from_secs(5))
+                .unwrap()
+                .expect("webhook not delivered");
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            assert_eq!(*request.method(), tiny_http::Method::Post);
+            assert_eq!(request.url(), "/hook");
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+                json!({
+                    "run_id": 2,
+                    "event": "change",
+                    "output_excerpt": "b\nc\nd",
+                    "diff": { "added": 2, "removed": 1 },
+                })
+            );
+            request.respond(Response::empty(status)).unwrap();
+        }
+
+        // NOTE: an accepted payload is not sent again
+        let retried = server.recv_timeout(Duration::from_millis(100)).unwrap();
+        assert!(retried.is_none());
+    }
+}
@@ -34,3 +34,9 @@ impl std::convert::From<crossbeam_channel::SendError<bool>> for BodaError {
         BodaError::Custom(value.to_string())
     }
 }
+
+impl std::convert::From<String> for BodaError {
+    fn from(value: String) -> Self {
+        BodaError::Custom(value)
+    }
+}
@@ -1,37 +1,219 @@
 mod command;
 mod error;
+mod server;
 mod state;
 mod ui;
 mod util;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Cli {
-    #[arg(short = 'n', long, default_value_t = 1.0)]
-    interval: f64,
+    #[command(subcommand)]
+    action: Option<Action>,
+
+    /// Seconds between runs, 1 by default. With `--watch-path`, runs only happen on this interval when it is given
+    #[arg(short = 'n', long)]
+    interval: Option<f64>,
 
     #[arg(short, long, default_value_t = 1)]
     concurrency: u8,
 
+    /// Keep at most this many runs
+    #[arg(long)]
+    max_history: Option<u32>,
+
+    /// Drop runs older than this, e.g. `30m`, `12h`, `7d`
+    #[arg(long, value_parser = util::chrono::parse_duration)]
+    max_age: Option<std::time::Duration>,
+
+    /// Prune the oldest runs once the database grows past this, e.g. `512MB`
+    #[arg(long, value_parser = util::size::parse_size)]
+    max_db_size: Option<u64>,
+
+    /// Never prune runs whose output changed or whose status was non-zero
+    #[arg(long)]
+    keep_notable: bool,
+
+    /// Keep at most this much of stdout and stderr per run, e.g. `1MB`
+    #[arg(long, value_parser = util::size::parse_size)]
+    max_output: Option<u64>,
+
+    /// Compress stored output with zstd
+    #[arg(long)]
+    compress: bool,
+
+    /// Number to extract from stdout and chart, repeatable: `[name=]re:<regex>`, `col:<column>[:<line>]` or `json:<path>`
+    #[arg(long, value_parser = state::metric::Rule::parse)]
+    metric: Vec<state::metric::Rule>,
+
+    /// Ring the terminal bell when a run fails or its output changes
+    #[arg(short = 'b', long)]
+    beep: bool,
+
+    /// Highlight the header border when a run fails or its output changes
+    #[arg(long)]
+    flash: bool,
+
+    /// Send a desktop notification through the terminal on these events: `fail`, `recover`, `change`
+    #[arg(long, value_delimiter = ',', value_parser = state::alert::Event::parse)]
+    notify: Vec<state::alert::Event>,
+
+    /// Escape sequence used by `--notify`, OSC 9 (iTerm2, kitty, WezTerm) or OSC 777 (foot, WezTerm)
+    #[arg(long, value_enum, default_value_t = ui::manager::Osc::Osc9)]
+    notify_osc: ui::manager::Osc,
+
+    /// URL to POST a JSON summary of the run to on `--webhook-on` events
+    #[arg(long)]
+    webhook: Option<String>,
+
+    /// Events sent to `--webhook`: `fail`, `recover`, `change`
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = state::alert::Event::parse,
+        default_value = "fail,recover"
+    )]
+    webhook_on: Vec<state::alert::Event>,
+
+    /// Address to serve history and live runs over HTTP/JSON on, e.g. `127.0.0.1:8080`
+    #[arg(long, value_name = "ADDR")]
+    serve: Option<String>,
+
+    /// Hook run through the shell when the output or status changes
+    #[arg(long, value_name = "HOOK")]
+    on_change: Option<String>,
+
+    /// Hook run through the shell for every failed run
+    #[arg(long, value_name = "HOOK")]
+    on_fail: Option<String>,
+
+    /// Hook run through the shell when the command succeeds again after failing
+    #[arg(long, value_name = "HOOK")]
+    on_recover: Option<String>,
+
+    /// Hook run through the shell for every run whose stdout matches the regex
+    #[arg(long, num_args = 2, value_names = ["REGEX", "HOOK"])]
+    on_match: Option<Vec<String>>,
+
+    /// Encoding used to display output, e.g. `latin-1`, `euc-kr`, `shift-jis`
+    #[arg(long, value_parser = util::encoding::Encoding::parse, default_value = "utf-8")]
+    encoding: util::encoding::Encoding,
+
+    /// Additional command to watch, repeatable. Prefix with `@<interval>` to override `-n`, e.g. `@5s df -h`
+    #[arg(short = 'e', long, value_parser = state::watch::Spec::parse)]
+    cmd: Vec<state::watch::Spec>,
+
+    /// File with one command per line, in the same format as `--cmd`
+    #[arg(long)]
+    profile: Option<std::path::PathBuf>,
+
+    /// Run when files matching the glob change, repeatable, e.g. `src/**/*.rs`
+    #[arg(long, value_name = "GLOB", value_parser = command::trigger::Pattern::parse)]
+    watch_path: Vec<command::trigger::Pattern>,
+
+    /// Wait for changes to settle this long before running, e.g. `100ms`, `1s`
+    #[arg(long, value_parser = util::chrono::parse_duration, default_value = "100ms")]
+    debounce: std::time::Duration,
+
+    /// Run the command directly instead of through a shell
+    #[arg(short = 'x', long, conflicts_with = "shell")]
+    exec: bool,
+
+    /// Shell used to run the command, defaults to `$SHELL`
+    #[arg(long)]
+    shell: Option<String>,
+
+    /// Working directory for the command
+    #[arg(long)]
+    cwd: Option<std::path::PathBuf>,
+
+    /// Extra environment variable for the command, repeatable
+    #[arg(long, value_name = "KEY=VAL", value_parser = state::environment::parse_var)]
+    env: Vec<(String, String)>,
+
+    /// File with `KEY=VAL` lines to add to the command's environment
+    #[arg(long)]
+    env_file: Option<std::path::PathBuf>,
+
+    /// File fed to the command's stdin on every run
+    #[arg(long, conflicts_with = "stdin")]
+    stdin_file: Option<std::path::PathBuf>,
+
+    /// String fed to the command's stdin on every run
+    #[arg(long)]
+    stdin: Option<String>,
+
     #[arg(last = true)]
     command: Vec<String>,
 }
 
+#[derive(Subcommand, Debug)]
+enum Action {
+    /// Show a running boda in this terminal, `q` detaches
+    Attach {
+        /// Session to attach to, or a prefix of it. Defaults to the newest one
+        session: Option<String>,
+    },
+    /// Send a command to a running boda: pause, resume, run-now [watch], set-interval <interval> [watch], quit or snapshot
+    Send {
+        /// Session to send to, or a prefix of it. Defaults to the newest one
+        #[arg(short, long)]
+        session: Option<String>,
+        #[arg(required = true, num_args = 1..)]
+        command: Vec<String>,
+    },
+}
+
 fn main() -> error::BodaResult<()> {
     util::log::setup();
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
+    match &cli.action {
+        Some(Action::Attach { session }) => return ui::attach::attach(session.as_deref()),
+        Some(Action::Send { session, command }) => {
+            return ui::control::send(session.as_deref(), &command.join(" "));
+        }
+        None => {}
+    }
+    if let Some(profile) = &cli.profile {
+        cli.cmd.extend(state::watch::Spec::load_profile(profile)?);
+    }
+    if let Some(env_file) = &cli.env_file {
+        // NOTE: `--env` takes precedence over the file
+        let mut env = state::environment::load_file(env_file)?;
+        env.append(&mut cli.env);
+        cli.env = env;
+    }
+
+    if let Some(on_match) = &cli.on_match {
+        regex::Regex::new(&on_match[0]).map_err(|e| format!("invalid --on-match regex: {}", e))?;
+    }
 
     let tempdir = std::env::temp_dir();
     let tempfile = ulid::Ulid::new().to_string();
     let filepath = tempdir.join(format!("{}.sqlite", tempfile));
 
+    // NOTE: bind before the tui takes over the terminal so errors are still readable
+    let server = cli
+        .serve
+        .as_deref()
+        .map(server::manager::Manager::new)
+        .transpose()?;
+    let attach = ui::attach::Server::new(&tempfile)?;
+
+    let notify_osc = cli.notify_osc;
     let state_manager = state::manager::Manager::new(cli, &filepath);
+    let state = state_manager.state.clone();
     let (command_manger, command_action_rx) = command::manager::Manager::new();
-    let (ui_manager, ui_action_rx) = ui::manager::Manager::new();
+    let (ui_manager, ui_action_rx) = ui::manager::Manager::new(notify_osc);
 
     let command_handle = command_manger.run(state_manager.state.clone());
+    if let Some(server) = server {
+        // NOTE: not joined, the server blocks on incoming requests and goes away with the process
+        server.run(state_manager.state.clone());
+    }
+    attach.run(state_manager.state.clone(), &ui_manager);
     let ui_handle = ui_manager.run(state_manager.state.clone());
     let (ui_state_handle, command_state_handle) =
         state_manager.run(ui_action_rx, command_action_rx);
@@ -45,6 +227,10 @@ fn main() -> error::BodaResult<()> {
     for handle in handles {
         handle.join().expect("unable to join thread");
     }
+    state.read().unwrap().global.cleanup();
+    if let Err(e) = std::fs::remove_file(ui::attach::socket_path(&tempfile)) {
+        log::error!("error remove socket: {}", e);
+    }
     println!("Backup at {:?}", filepath);
     Ok(())
 }
@@ -0,0 +1,216 @@
+use std::{
+    io::{self, Write},
+    sync::{Arc, RwLock},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crossbeam_channel::RecvTimeoutError;
+use log::{debug, error, info};
+use serde_json::{Value, json};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{error::BodaResult, state};
+
+use super::prometheus;
+
+// NOTE: proxies tend to close idle connections after 30-60s
+const KEEPALIVE: Duration = Duration::from_secs(15);
+
+/// Read-only HTTP/JSON view of the watches for `--serve`.
+pub struct Manager {
+    server: Server,
+}
+
+impl Manager {
+    pub fn new(addr: &str) -> BodaResult<Manager> {
+        let server = Server::http(addr).map_err(|e| format!("cannot serve on {}: {}", addr, e))?;
+        info!("serving on {}", addr);
+        Ok(Manager { server })
+    }
+
+    pub fn run(self, state: Arc<RwLock<state::state::State>>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            for request in self.server.incoming_requests() {
+                let state = state.clone();
+                // NOTE: event streams stay open, so every request gets its own thread
+                thread::spawn(move || handle(request, &state));
+            }
+        })
+    }
+}
+
+fn handle(request: Request, state: &Arc<RwLock<state::state::State>>) {
+    debug!("{} {}", request.method(), request.url());
+    if *request.method() != Method::Get {
+        respond(request, 405, json!({ "error": "method not allowed" }));
+        return;
+    }
+
+    let path = request.url().split('?').next().unwrap_or("").to_string();
+    let segments = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let body = match segments.as_slice() {
+        ["history"] => Some(history(&state.read().unwrap())),
+        ["latest"] => Some(latest(&state.read().unwrap())),
+        ["runs", id] => id
+            .parse()
+            .ok()
+            .and_then(|id| run(&state.read().unwrap(), id)),
+        ["metrics"] => {
+            let body = prometheus::render(&state.read().unwrap());
+            let response = Response::from_string(body)
+                .with_header(Header::from_bytes("Content-Type", prometheus::CONTENT_TYPE).unwrap());
+            if let Err(e) = request.respond(response) {
+                error!("error respond: {}", e);
+            }
+            return;
+        }
+        ["events"] => {
+            stream_events(request, state);
+            return;
+        }
+        _ => None,
+    };
+
+    match body {
+        Some(body) => respond(request, 200, body),
+        None => respond(request, 404, json!({ "error": "not found" })),
+    }
+}
+
+fn respond(request: Request, status: u16, body: Value) {
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+    if let Err(e) = request.respond(response) {
+        error!("error respond: {}", e);
+    }
+}
+
+/// Runs of every watch, newest first.
+fn history(state: &state::state::State) -> Value {
+    let watches = (0..state.global.watches.len())
+        .map(|watch| {
+            let runs = state
+                .global
+                .get_history(watch)
+                .iter()
+                .map(|summary| summary_json(state, watch, summary))
+                .collect::<Vec<_>>();
+            json!({
+                "watch": watch,
+                "command": state.global.watches[watch].command.join(" "),
+                "interval_ms": state.global.watches[watch].interval.as_millis() as u64,
+                "runs": runs,
+            })
+        })
+        .collect();
+    Value::Array(watches)
+}
+
+/// Latest completed run of every watch, with its output.
+fn latest(state: &state::state::State) -> Value {
+    let watches = (0..state.global.watches.len())
+        .map(|watch| {
+            let id = state
+                .global
+                .get_history(watch)
+                .iter()
+                .find(|summary| summary.status.is_some())
+                .map(|summary| summary.id);
+            json!({
+                "watch": watch,
+                "command": state.global.watches[watch].command.join(" "),
+                "run": id.and_then(|id| run(state, id)),
+            })
+        })
+        .collect();
+    Value::Array(watches)
+}
+
+/// A run with its output, decoded with `--encoding`.
+fn run(state: &state::state::State, id: i64) -> Option<Value> {
+    let watch = state.global.find_watch(id)?;
+    let target = state::state::TargetCommand::Target(id);
+    let summary = state.global.get_target_summary(watch, &target)?;
+    let result = state.global.get_target_command_result(watch, &target);
+
+    let mut value = summary_json(state, watch, summary);
+    let decode =
+        |output: Option<&Vec<u8>>| output.map(|output| state.global.encoding.decode(output));
+    value["stdout"] = json!(decode(result.as_ref().and_then(|r| r.stdout.as_ref())));
+    value["stderr"] = json!(decode(result.as_ref().and_then(|r| r.stderr.as_ref())));
+    value["binary"] = json!(result.is_some_and(|r| r.is_binary()));
+    Some(value)
+}
+
+fn summary_json(
+    state: &state::state::State,
+    watch: usize,
+    summary: &state::state::CommandResultSummary,
+) -> Value {
+    let metrics = state
+        .global
+        .metrics
+        .iter()
+        .zip(&summary.metrics)
+        .filter_map(|(rule, value)| Some((rule.name.clone(), json!((*value)?))))
+        .collect::<serde_json::Map<_, _>>();
+
+    json!({
+        "id": summary.id,
+        "watch": watch,
+        "command": state.global.watches[watch].command.join(" "),
+        "start": summary.start.to_rfc3339(),
+        "end": summary.end.map(|end| end.to_rfc3339()),
+        "duration_ms": summary.end.map(|end| (end - summary.start).num_milliseconds()),
+        "status": summary.status,
+        "signal": summary.signal,
+        "changed": summary.changed,
+        "truncated": summary.truncated,
+        "stdout_size": summary.stdout_size,
+        "stderr_size": summary.stderr_size,
+        "stdout_lines": summary.stdout_lines,
+        "stderr_lines": summary.stderr_lines,
+        "usage": summary.usage.map(|usage| json!({
+            "user_time_ms": usage.user_time.as_millis() as u64,
+            "system_time_ms": usage.system_time.as_millis() as u64,
+            "max_rss": usage.max_rss,
+            "read_bytes": usage.read_bytes,
+            "write_bytes": usage.write_bytes,
+        })),
+        "metrics": metrics,
+    })
+}
+
+/// Server-sent events, one `run` event per finished run, until the client goes away.
+fn stream_events(request: Request, state: &Arc<RwLock<state::state::State>>) {
+    let rx = state.read().unwrap().global.subscribe();
+    // NOTE: tiny_http buffers chunked bodies, write to the socket directly so events are not delayed
+    let mut writer = request.into_writer();
+
+    let mut send = |data: &[u8]| -> io::Result<()> {
+        writer.write_all(data)?;
+        writer.flush()
+    };
+    let result = send(
+        b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+    )
+    .and_then(|_| loop {
+        match rx.recv_timeout(KEEPALIVE) {
+            Ok((watch, summary)) => {
+                let data = summary_json(&state.read().unwrap(), watch, &summary);
+                send(format!("event: run\ndata: {}\n\n", data).as_bytes())?;
+            }
+            Err(RecvTimeoutError::Timeout) => send(b": keepalive\n\n")?,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    });
+
+    if let Err(e) = result {
+        debug!("event stream closed: {}", e);
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt::Write;
+
+use crate::state;
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Counters and gauges of every watch in the Prometheus text format.
+pub fn render(state: &state::state::State) -> String {
+    let watches = &state.global.watches;
+    let labels = watches
+        .iter()
+        .enumerate()
+        .map(|(watch, w)| {
+            format!(
+                "watch=\"{}\",command=\"{}\"",
+                watch,
+                escape(&w.command.join(" "))
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = String::new();
+    header(
+        &mut out,
+        "boda_runs_total",
+        "counter",
+        "Finished runs of the command.",
+    );
+    for (watch, w) in watches.iter().enumerate() {
+        writeln!(out, "boda_runs_total{{{}}} {}", labels[watch], w.stats.runs).unwrap();
+    }
+
+    header(
+        &mut out,
+        "boda_failures_total",
+        "counter",
+        "Failed runs of the command by exit code.",
+    );
+    for (watch, w) in watches.iter().enumerate() {
+        for (code, count) in &w.stats.failures {
+            writeln!(
+                out,
+                "boda_failures_total{{{},code=\"{}\"}} {}",
+                labels[watch], code, count
+            )
+            .unwrap();
+        }
+    }
+
+    header(
+        &mut out,
+        "boda_output_changes_total",
+        "counter",
+        "Runs whose output or status differs from the previous run.",
+    );
+    for (watch, w) in watches.iter().enumerate() {
+        writeln!(
+            out,
+            "boda_output_changes_total{{{}}} {}",
+            labels[watch], w.stats.changes
+        )
+        .unwrap();
+    }
+
+    header(
+        &mut out,
+        "boda_last_status",
+        "gauge",
+        "Exit status of the latest finished run.",
+    );
+    for (watch, w) in watches.iter().enumerate() {
+        if let Some(status) = w.stats.last_status {
+            writeln!(out, "boda_last_status{{{}}} {}", labels[watch], status).unwrap();
+        }
+    }
+
+    header(
+        &mut out,
+        "boda_last_duration_seconds",
+        "gauge",
+        "Duration of the latest finished run.",
+    );
+    for (watch, w) in watches.iter().enumerate() {
+        if let Some(duration) = w.stats.last_duration {
+            writeln!(
+                out,
+                "boda_last_duration_seconds{{{}}} {}",
+                labels[watch],
+                duration.as_secs_f64()
+            )
+            .unwrap();
+        }
+    }
+
+    header(
+        &mut out,
+        "boda_running",
+        "gauge",
+        "Runs of the command in flight.",
+    );
+    for (watch, schedule) in state.command.schedules.iter().enumerate() {
+        writeln!(
+            out,
+            "boda_running{{{}}} {}",
+            labels[watch], schedule.running_count
+        )
+        .unwrap();
+    }
+
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
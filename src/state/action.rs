@@ -1,7 +1,12 @@
-use std::time::Instant;
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 use crate::util;
 
+use super::blob;
+
 #[derive(Debug)]
 pub enum Ui {
     Quit,
@@ -11,8 +16,33 @@ pub enum Ui {
     SelectNext,
     SelectPrev,
     SelectLatest,
+    SelectNextChanged,
+    SelectPrevChanged,
+    SelectFirst,
+    SelectPageDown,
+    SelectPageUp,
+    GoTo(GoTo),
+
+    FocusNext,
+    FocusPrev,
+    Focus(usize),
+    ToggleLayout,
+
+    ToggleGroupHistory,
+    ToggleExpandGroup,
 
     ToggleShowHelp,
+    ToggleShowDetails,
+    ToggleShowChart,
+    ToggleShowMetrics,
+    ToggleHex,
+    ToggleSilence,
+
+    TogglePause,
+    SetPaused(bool),
+    /// Run the watch on the next tick, the focused one when `None`.
+    RunNow(Option<usize>),
+    SetInterval(usize, Duration),
 
     ScrollDown,
     ScrollUp,
@@ -23,11 +53,106 @@ pub enum Ui {
 #[derive(Debug)]
 pub enum Command {
     RunResult(
+        usize,
         util::chrono::DateTime,
         util::chrono::DateTime,
-        String,
-        String,
+        Box<RunOutput>,
         u8,
     ),
-    StartRun(Instant, util::chrono::DateTime),
+    Chunk(usize, util::chrono::DateTime, Stream, Vec<u8>),
+    StartRun(
+        usize,
+        Instant,
+        util::chrono::DateTime,
+        crossbeam_channel::Sender<Started>,
+    ),
+    /// Files matching `--watch-path` changed, an empty path when events were lost.
+    PathsChanged(Vec<PathBuf>),
+}
+
+/// Captured output of a finished run, as raw bytes.
+#[derive(Debug, Default)]
+pub struct RunOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// Size before `--max-output` truncation.
+    pub stdout_size: u64,
+    pub stderr_size: u64,
+    /// `None` when the command could not be spawned.
+    pub usage: Option<Usage>,
+    /// Signal that killed the process, if any.
+    pub signal: Option<u8>,
+    /// `stdout` and `stderr` ready to store, prepared by the command thread to keep the work off the state lock.
+    pub blobs: Option<(blob::Blob, blob::Blob)>,
+}
+
+/// Resource usage of a run, including descendants the command waited for.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Usage {
+    pub user_time: Duration,
+    pub system_time: Duration,
+    /// Peak resident set size in bytes.
+    pub max_rss: u64,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Reply to `Command::StartRun`, describing the run about to be spawned.
+#[derive(Debug)]
+pub struct Started {
+    pub id: i64,
+    pub iteration: u64,
+    pub prev_status: Option<u8>,
+    pub prev_output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GoTo {
+    Run(i64),
+    Time(util::chrono::DateTime),
+}
+
+impl GoTo {
+    /// Parse a run number (`123`, `#123`), a time of day (`14:32`, `14:32:10`)
+    /// or a time relative to now (`-15m`).
+    pub fn parse(input: &str) -> Result<GoTo, String> {
+        let input = input.trim();
+
+        if let Some(relative) = input.strip_prefix('-') {
+            let duration = util::chrono::parse_duration(relative)?;
+            let duration = chrono::TimeDelta::from_std(duration).map_err(|e| e.to_string())?;
+            return chrono::Local::now()
+                .checked_sub_signed(duration)
+                .map(GoTo::Time)
+                .ok_or_else(|| format!("time out of range: {input}"));
+        }
+
+        if input.contains(':') {
+            let time = chrono::NaiveTime::parse_from_str(input, "%H:%M:%S")
+                .or_else(|_| chrono::NaiveTime::parse_from_str(input, "%H:%M"))
+                .map_err(|_| format!("invalid time: {input}"))?;
+            let mut date = chrono::Local::now().date_naive();
+            if time > chrono::Local::now().time() {
+                date = date.pred_opt().unwrap_or(date);
+            }
+            return date
+                .and_time(time)
+                .and_local_timezone(chrono::Local)
+                .earliest()
+                .map(GoTo::Time)
+                .ok_or_else(|| format!("invalid time: {input}"));
+        }
+
+        input
+            .trim_start_matches('#')
+            .parse::<i64>()
+            .map(GoTo::Run)
+            .map_err(|_| format!("invalid run number: {input}"))
+    }
 }
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+
+/// Condition on a finished run that fires a hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// Output or status differs from the previous run.
+    Change,
+    /// A run failed. Notifications and webhooks only get the first failure after a success.
+    Fail,
+    /// A run succeeded after a failed one.
+    Recover,
+    /// Stdout matched `--on-match`.
+    Match,
+}
+
+impl Event {
+    /// Accepts the events a notification can be bound to: `change`, `fail` and `recover`.
+    pub fn parse(input: &str) -> Result<Event, String> {
+        match input.trim() {
+            "change" => Ok(Event::Change),
+            "fail" => Ok(Event::Fail),
+            "recover" => Ok(Event::Recover),
+            _ => Err(format!(
+                "unknown event: {input}, expected change, fail or recover"
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::Change => "change",
+            Event::Fail => "fail",
+            Event::Recover => "recover",
+            Event::Match => "match",
+        }
+    }
+}
+
+/// Hook commands given with `--on-*`, run through the shell.
+#[derive(Debug, Default)]
+pub struct Hooks {
+    pub on_change: Option<String>,
+    pub on_fail: Option<String>,
+    pub on_recover: Option<String>,
+    pub on_match: Option<(Regex, String)>,
+}
+
+/// A hook to run for a finished run.
+#[derive(Debug)]
+pub struct Alert {
+    pub event: Event,
+    pub hook: String,
+    /// Run metadata passed as `BODA_*` variables.
+    pub vars: Vec<(String, String)>,
+    /// Stdout of the run, fed to the hook's stdin.
+    pub stdin: Vec<u8>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.on_change.is_none()
+            && self.on_fail.is_none()
+            && self.on_recover.is_none()
+            && self.on_match.is_none()
+    }
+
+    /// Hooks fired by a run with `status` following one with `prev_status`.
+    pub fn events(
+        &self,
+        prev_status: Option<u8>,
+        status: u8,
+        changed: bool,
+        stdout: &str,
+    ) -> Vec<(Event, &str)> {
+        // NOTE: unlike notifications, fail and match hooks fire on every run meeting the condition
+        let mut events = transitions(prev_status, status, changed)
+            .into_iter()
+            .filter(|event| *event != Event::Fail)
+            .chain((status != 0).then_some(Event::Fail))
+            .filter_map(|event| {
+                let hook = match event {
+                    Event::Change => &self.on_change,
+                    Event::Fail => &self.on_fail,
+                    Event::Recover => &self.on_recover,
+                    Event::Match => return None,
+                };
+                Some((event, hook.as_deref()?))
+            })
+            .collect::<Vec<_>>();
+        if let Some((regex, hook)) = &self.on_match
+            && regex.is_match(stdout)
+        {
+            events.push((Event::Match, hook.as_str()));
+        }
+
+        events
+    }
+}
+
+/// Change, fail and recover events of a run with `status` following one with `prev_status`.
+pub fn transitions(prev_status: Option<u8>, status: u8, changed: bool) -> Vec<Event> {
+    let mut events = vec![];
+    if prev_status.is_some() && changed {
+        events.push(Event::Change);
+    }
+    if status != 0 && prev_status.is_none_or(|prev| prev == 0) {
+        events.push(Event::Fail);
+    }
+    if status == 0 && prev_status.is_some_and(|prev| prev != 0) {
+        events.push(Event::Recover);
+    }
+    events
+}
+
+/// Lines added and removed going from `old` to `new`, ignoring their order.
+pub fn diff_summary(old: &str, new: &str) -> (usize, usize) {
+    let mut counts = HashMap::<&str, isize>::new();
+    for line in old.lines() {
+        *counts.entry(line).or_default() -= 1;
+    }
+    for line in new.lines() {
+        *counts.entry(line).or_default() += 1;
+    }
+
+    counts.values().fold((0, 0), |(added, removed), count| {
+        if *count > 0 {
+            (added + *count as usize, removed)
+        } else {
+            (added, removed + count.unsigned_abs())
+        }
+    })
+}
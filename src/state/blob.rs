@@ -0,0 +1,79 @@
+use std::fmt::Write;
+
+use rusqlite::{Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+
+const COMPRESSION_LEVEL: i32 = 3;
+
+pub fn hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .fold(String::with_capacity(64), |mut hash, byte| {
+            write!(hash, "{:02x}", byte).unwrap();
+            hash
+        })
+}
+
+/// Content hashed and, with `--compress`, compressed, ready to be stored.
+#[derive(Debug)]
+pub struct Blob {
+    pub hash: String,
+    data: Vec<u8>,
+    compressed: bool,
+}
+
+impl Blob {
+    pub fn new(data: &[u8], compress: bool) -> Blob {
+        let (encoded, compressed) = if compress {
+            match zstd::encode_all(data, COMPRESSION_LEVEL) {
+                Ok(encoded) if encoded.len() < data.len() => (encoded, true),
+                _ => (data.to_vec(), false),
+            }
+        } else {
+            (data.to_vec(), false)
+        };
+        Blob {
+            hash: hash(data),
+            data: encoded,
+            compressed,
+        }
+    }
+}
+
+/// Store `blob` keyed by its hash. Identical content is stored only once.
+pub fn store(conn: &Connection, blob: &Blob) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO blob (hash, data, compressed) VALUES (?1, ?2, ?3)",
+        (&blob.hash, &blob.data, blob.compressed),
+    )?;
+    Ok(())
+}
+
+pub fn load(conn: &Connection, hash: &str) -> rusqlite::Result<Option<Vec<u8>>> {
+    let row = conn
+        .query_row(
+            "SELECT data, compressed FROM blob WHERE hash=?1",
+            [hash],
+            |row| Ok((row.get::<usize, Vec<u8>>(0)?, row.get::<usize, bool>(1)?)),
+        )
+        .optional()?;
+
+    Ok(row.map(|(data, compressed)| {
+        if compressed {
+            zstd::decode_all(data.as_slice()).unwrap_or_default()
+        } else {
+            data
+        }
+    }))
+}
+
+/// Delete blobs no longer referenced by any `command_result` row.
+pub fn prune_orphans(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.execute(
+        "DELETE FROM blob WHERE hash NOT IN (
+            SELECT stdout_hash FROM command_result WHERE stdout_hash IS NOT NULL
+            UNION SELECT stderr_hash FROM command_result WHERE stderr_hash IS NOT NULL
+        )",
+        (),
+    )
+}
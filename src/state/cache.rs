@@ -0,0 +1,131 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use crate::util;
+
+use super::{
+    action::Stream,
+    state::{CommandResult, CommandResultSummary},
+};
+
+// NOTE: full results hold the whole stdout/stderr, so only a handful are kept around
+const RESULT_CAPACITY: usize = 32;
+
+/// In-memory view over `command_result`, kept in sync with `action::Command` events so the
+/// render loop does not have to hit SQLite on every frame.
+#[derive(Debug, Default)]
+pub struct Cache {
+    // NOTE: newest first
+    summaries: VecDeque<CommandResultSummary>,
+    // NOTE: most recently used first
+    results: Mutex<VecDeque<Arc<CommandResult>>>,
+    // NOTE: output streamed so far by runs still in flight
+    partials: HashMap<i64, Arc<CommandResult>>,
+}
+
+impl Cache {
+    pub fn summaries(&self) -> &VecDeque<CommandResultSummary> {
+        &self.summaries
+    }
+
+    pub fn summary(&self, id: i64) -> Option<&CommandResultSummary> {
+        self.summaries.iter().find(|summary| summary.id == id)
+    }
+
+    pub fn push_summary(&mut self, summary: CommandResultSummary) {
+        self.summaries.push_front(summary);
+    }
+
+    pub fn summary_by_start_mut(
+        &mut self,
+        start: util::chrono::DateTime,
+    ) -> Option<&mut CommandResultSummary> {
+        self.summaries
+            .iter_mut()
+            .find(|summary| summary.start == start)
+    }
+
+    pub fn remove(&mut self, ids: &[i64]) {
+        if ids.is_empty() {
+            return;
+        }
+
+        self.summaries.retain(|summary| !ids.contains(&summary.id));
+        self.partials.retain(|id, _| !ids.contains(id));
+        self.results
+            .lock()
+            .unwrap()
+            .retain(|result| !ids.contains(&result.id));
+    }
+
+    /// The run shown as `Latest`: the newest run that completed or already streamed output.
+    pub fn latest_visible(&self) -> Option<&CommandResultSummary> {
+        self.summaries.iter().find(|summary| {
+            summary.status.is_some()
+                || self
+                    .partials
+                    .get(&summary.id)
+                    .is_some_and(|partial| partial.has_output())
+        })
+    }
+
+    pub fn partial(&self, id: i64) -> Option<Arc<CommandResult>> {
+        self.partials.get(&id).cloned()
+    }
+
+    pub fn append_partial(
+        &mut self,
+        id: i64,
+        start: util::chrono::DateTime,
+        stream: Stream,
+        chunk: &[u8],
+    ) {
+        let partial = self.partials.entry(id).or_insert_with(|| {
+            Arc::new(CommandResult {
+                id,
+                start,
+                stdout: None,
+                stderr: None,
+                stdout_size: None,
+                stderr_size: None,
+                status: None,
+            })
+        });
+
+        // NOTE: clones only if a renderer still holds the previous snapshot
+        let partial = Arc::make_mut(partial);
+        let output = match stream {
+            Stream::Stdout => &mut partial.stdout,
+            Stream::Stderr => &mut partial.stderr,
+        };
+        output.get_or_insert_with(Vec::new).extend_from_slice(chunk);
+    }
+
+    pub fn remove_partial(&mut self, id: i64) {
+        self.partials.remove(&id);
+    }
+
+    pub fn latest_completed(&self) -> Option<&CommandResultSummary> {
+        self.summaries
+            .iter()
+            .find(|summary| summary.status.is_some())
+    }
+
+    pub fn get_result(&self, id: i64) -> Option<Arc<CommandResult>> {
+        let mut results = self.results.lock().unwrap();
+        let index = results.iter().position(|result| result.id == id)?;
+        let result = results.remove(index)?;
+        results.push_front(result.clone());
+
+        Some(result)
+    }
+
+    pub fn put_result(&self, result: Arc<CommandResult>) {
+        let mut results = self.results.lock().unwrap();
+        results.retain(|it| it.id != result.id);
+        results.push_front(result);
+        results.truncate(RESULT_CAPACITY);
+    }
+}
@@ -0,0 +1,60 @@
+use std::{fs, io, path::Path, path::PathBuf};
+
+use crate::error::BodaResult;
+
+/// Working directory and extra variables for the watched command.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    pub cwd: Option<PathBuf>,
+    pub vars: Vec<(String, String)>,
+    /// Fed to every run, stdin is null when unset.
+    pub stdin: Option<Stdin>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Stdin {
+    File(PathBuf),
+    Text(String),
+}
+
+impl Stdin {
+    pub fn read(&self) -> io::Result<Vec<u8>> {
+        match self {
+            Stdin::File(path) => fs::read(path)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e))),
+            Stdin::Text(text) => Ok(text.clone().into_bytes()),
+        }
+    }
+}
+
+pub fn parse_var(input: &str) -> Result<(String, String), String> {
+    let (key, value) = input
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VAL: {input}"))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(format!("empty key: {input}"));
+    }
+
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Read `KEY=VAL` lines, skipping blank lines and `#` comments. Values may be quoted.
+pub fn load_file(path: &Path) -> BodaResult<Vec<(String, String)>> {
+    fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = parse_var(line).map_err(|e| format!("{}: {}", path.display(), e))?;
+            let value = value.trim();
+            let value = ['"', '\'']
+                .iter()
+                .find_map(|q| value.strip_prefix(*q)?.strip_suffix(*q))
+                .unwrap_or(value);
+
+            Ok((key, value.to_string()))
+        })
+        .collect()
+}
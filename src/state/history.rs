@@ -0,0 +1,105 @@
+use std::collections::{HashSet, VecDeque};
+
+use super::state::CommandResultSummary;
+
+/// A line of the history pane, indexing into the newest-first history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Row {
+    Run { index: usize, grouped: bool },
+    Group { newest: usize, oldest: usize },
+}
+
+impl Row {
+    pub fn contains(&self, index: usize) -> bool {
+        match *self {
+            Row::Run { index: it, .. } => it == index,
+            Row::Group { newest, oldest } => (newest..=oldest).contains(&index),
+        }
+    }
+
+    pub fn newest(&self) -> usize {
+        match *self {
+            Row::Run { index, .. } => index,
+            Row::Group { newest, .. } => newest,
+        }
+    }
+}
+
+/// Key used to remember an expanded group, stable while newer unchanged runs join it.
+pub fn group_key(history: &VecDeque<CommandResultSummary>, oldest: usize) -> i64 {
+    history[oldest].id
+}
+
+/// Ranges `(newest, oldest)` of two or more consecutive runs whose output and status were unchanged.
+pub fn unchanged_ranges(history: &VecDeque<CommandResultSummary>) -> Vec<(usize, usize)> {
+    let mut ranges = vec![];
+    let mut start = None;
+
+    for (index, summary) in history.iter().enumerate() {
+        if summary.changed == Some(false) {
+            start.get_or_insert(index);
+            continue;
+        }
+
+        if let Some(newest) = start.take()
+            && index - newest > 1
+        {
+            ranges.push((newest, index - 1));
+        }
+    }
+    if let Some(newest) = start
+        && history.len() - newest > 1
+    {
+        ranges.push((newest, history.len() - 1));
+    }
+
+    ranges
+}
+
+pub fn rows(
+    history: &VecDeque<CommandResultSummary>,
+    grouped: bool,
+    expanded: &HashSet<i64>,
+) -> Vec<Row> {
+    if !grouped {
+        return (0..history.len())
+            .map(|index| Row::Run {
+                index,
+                grouped: false,
+            })
+            .collect();
+    }
+
+    let mut rows = Vec::with_capacity(history.len());
+    let mut ranges = unchanged_ranges(history).into_iter().peekable();
+    let mut index = 0;
+
+    while index < history.len() {
+        match ranges.next_if(|(newest, _)| *newest == index) {
+            Some((newest, oldest)) if expanded.contains(&group_key(history, oldest)) => {
+                rows.extend((newest..=oldest).map(|index| Row::Run {
+                    index,
+                    grouped: true,
+                }));
+                index = oldest + 1;
+            }
+            Some((newest, oldest)) => {
+                rows.push(Row::Group { newest, oldest });
+                index = oldest + 1;
+            }
+            None => {
+                rows.push(Row::Run {
+                    index,
+                    grouped: false,
+                });
+                index += 1;
+            }
+        }
+    }
+
+    rows
+}
+
+pub fn row_position(rows: &[Row], index: usize) -> Option<usize> {
+    rows.iter().position(|row| row.contains(index))
+}
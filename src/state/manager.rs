@@ -2,14 +2,18 @@ use std::{
     path::PathBuf,
     sync::{Arc, RwLock},
     thread,
+    time::Instant,
 };
 
 use crossbeam_channel::select;
-use log::{debug, info};
+use log::{debug, error, info};
 
-use crate::Cli;
+use crate::{Cli, command::hook};
 
-use super::{action, state};
+use super::{action, alert, history, state};
+
+// NOTE: fixed because UI action handling does not receive the history pane height
+const HISTORY_PAGE: isize = 10;
 
 #[derive(Debug, Clone)]
 pub struct Manager {
@@ -69,6 +73,8 @@ impl Manager {
 
     fn handle_ui_action(&self, ui_action: action::Ui) {
         let mut state = self.state.write().unwrap();
+        let state = &mut *state;
+        let focus = state.ui.focus;
         match ui_action {
             action::Ui::Quit => {
                 debug!("received quit");
@@ -76,31 +82,31 @@ impl Manager {
                 state.global.running = false;
             }
             action::Ui::ScrollUp => {
-                if state.ui.vertical_scroll > 0 {
-                    state.ui.vertical_scroll -= 1;
+                if state.ui.view_mut().vertical_scroll > 0 {
+                    state.ui.view_mut().vertical_scroll -= 1;
                 }
             }
             action::Ui::ScrollDown => {
                 let length = match state
                     .global
-                    .get_target_command_result(&state.ui.target_command)
+                    .get_target_command_result(focus, &state.ui.view().target_command)
                 {
-                    Some(r) => r.get_content().len(),
+                    Some(r) => r.content_len(&state.global.encoding, state.ui.view().hex),
                     _ => return,
                 };
 
-                if (length - 1) as u16 <= state.ui.vertical_scroll {
+                if length.saturating_sub(1) as u16 <= state.ui.view_mut().vertical_scroll {
                     return;
                 }
 
-                state.ui.vertical_scroll += 1;
+                state.ui.view_mut().vertical_scroll += 1;
             }
             action::Ui::ScrollHalfDown => {
                 let length = match state
                     .global
-                    .get_target_command_result(&state.ui.target_command)
+                    .get_target_command_result(focus, &state.ui.view().target_command)
                 {
-                    Some(r) => r.get_content().len(),
+                    Some(r) => r.content_len(&state.global.encoding, state.ui.view().hex),
                     _ => return,
                 };
 
@@ -109,11 +115,16 @@ impl Manager {
                 }
 
                 let max_scroll = (length - 1) as u16;
-                state.ui.vertical_scroll =
-                    state.ui.vertical_scroll.saturating_add(10).min(max_scroll);
+                state.ui.view_mut().vertical_scroll = state
+                    .ui
+                    .view_mut()
+                    .vertical_scroll
+                    .saturating_add(10)
+                    .min(max_scroll);
             }
             action::Ui::ScrollHalfUp => {
-                state.ui.vertical_scroll = state.ui.vertical_scroll.saturating_sub(10);
+                state.ui.view_mut().vertical_scroll =
+                    state.ui.view_mut().vertical_scroll.saturating_sub(10);
             }
             action::Ui::ToggleShowHistory => {
                 state.ui.show_history = !state.ui.show_history;
@@ -121,28 +132,129 @@ impl Manager {
             action::Ui::ToggleShowHelp => {
                 state.ui.show_help = !state.ui.show_help;
             }
-            action::Ui::SelectNext => match state.ui.target_command {
-                state::TargetCommand::Latest => {
-                    state.ui.target_command =
-                        state::TargetCommand::Target(state.global.get_history()[0].id);
-                }
-                state::TargetCommand::Target(id) => {
-                    let id = if id == 0 { 0 } else { id - 1 };
-                    state.ui.target_command = state::TargetCommand::Target(id);
-                }
-            },
-            action::Ui::SelectPrev => match state.ui.target_command {
-                state::TargetCommand::Latest => {}
-                state::TargetCommand::Target(id) => {
-                    if state.global.get_history()[0].id == id {
-                        state.ui.target_command = state::TargetCommand::Latest
-                    } else {
-                        state.ui.target_command = state::TargetCommand::Target(id + 1)
+            action::Ui::ToggleShowDetails => {
+                state.ui.show_details = !state.ui.show_details;
+            }
+            action::Ui::ToggleShowChart => {
+                state.ui.show_chart = !state.ui.show_chart;
+            }
+            action::Ui::ToggleShowMetrics => {
+                state.ui.show_metrics = !state.ui.show_metrics;
+            }
+            action::Ui::ToggleSilence => {
+                state.ui.silenced = !state.ui.silenced;
+            }
+            action::Ui::ToggleHex => {
+                let view = state.ui.view_mut();
+                view.hex = !view.hex;
+                view.vertical_scroll = 0;
+            }
+            action::Ui::SelectNext => move_selection(state, 1),
+            action::Ui::SelectPrev => move_selection(state, -1),
+            action::Ui::SelectPageDown => move_selection(state, HISTORY_PAGE),
+            action::Ui::SelectPageUp => move_selection(state, -HISTORY_PAGE),
+            action::Ui::SelectFirst => move_selection(state, isize::MAX),
+            action::Ui::GoTo(goto) => {
+                let history = state.global.get_history(focus);
+                let nearest = match goto {
+                    action::GoTo::Run(id) => {
+                        history.iter().min_by_key(|summary| summary.id.abs_diff(id))
                     }
+                    action::GoTo::Time(time) => history
+                        .iter()
+                        .min_by_key(|summary| (summary.start - time).abs()),
+                };
+
+                if let Some(nearest) = nearest {
+                    state.ui.view_mut().target_command = state::TargetCommand::Target(nearest.id);
+                }
+            }
+            action::Ui::SelectNextChanged => {
+                let start = state.target_position().map_or(0, |position| position + 1);
+                let next = state
+                    .global
+                    .get_history(focus)
+                    .iter()
+                    .skip(start)
+                    .find(|summary| summary.changed == Some(true));
+
+                if let Some(next) = next {
+                    state.ui.view_mut().target_command = state::TargetCommand::Target(next.id);
+                }
+            }
+            action::Ui::SelectPrevChanged => {
+                let end = match state.target_position() {
+                    Some(position) => position,
+                    None => return,
+                };
+                let prev = state
+                    .global
+                    .get_history(focus)
+                    .iter()
+                    .take(end)
+                    .rev()
+                    .find(|summary| summary.changed == Some(true));
+
+                state.ui.view_mut().target_command = match prev {
+                    Some(prev) => state::TargetCommand::Target(prev.id),
+                    None => state::TargetCommand::Latest,
+                };
+            }
+            action::Ui::ToggleGroupHistory => {
+                state.ui.group_history = !state.ui.group_history;
+            }
+            action::Ui::ToggleExpandGroup => {
+                let position = match state.target_position() {
+                    Some(position) => position,
+                    None => return,
+                };
+                let history = state.global.get_history(focus);
+                let key = history::unchanged_ranges(history)
+                    .into_iter()
+                    .find(|(newest, oldest)| (*newest..=*oldest).contains(&position))
+                    .map(|(_, oldest)| history::group_key(history, oldest));
+
+                if let Some(key) = key
+                    && !state.ui.view_mut().expanded_groups.remove(&key)
+                {
+                    state.ui.view_mut().expanded_groups.insert(key);
+                }
+            }
+            action::Ui::FocusNext => {
+                state.ui.focus = (focus + 1) % state.ui.views.len();
+            }
+            action::Ui::FocusPrev => {
+                state.ui.focus = (focus + state.ui.views.len() - 1) % state.ui.views.len();
+            }
+            action::Ui::Focus(watch) => {
+                if watch < state.ui.views.len() {
+                    state.ui.focus = watch;
                 }
-            },
+            }
+            action::Ui::ToggleLayout => {
+                state.ui.layout = match state.ui.layout {
+                    state::Layout::Tiles => state::Layout::Tabs,
+                    state::Layout::Tabs => state::Layout::Tiles,
+                };
+            }
             action::Ui::SelectLatest => {
-                state.ui.target_command = state::TargetCommand::Latest;
+                state.ui.view_mut().target_command = state::TargetCommand::Latest;
+            }
+            action::Ui::TogglePause => {
+                state.command.paused = !state.command.paused;
+            }
+            action::Ui::SetPaused(paused) => {
+                state.command.paused = paused;
+            }
+            action::Ui::RunNow(watch) => {
+                if let Some(schedule) = state.command.schedules.get_mut(watch.unwrap_or(focus)) {
+                    schedule.run_now = true;
+                }
+            }
+            action::Ui::SetInterval(watch, interval) => {
+                if watch < state.global.watches.len() {
+                    state.global.set_interval(watch, interval);
+                }
             }
         }
     }
@@ -150,17 +262,123 @@ impl Manager {
     fn handle_command_action(&self, command_action: action::Command) {
         let mut state = self.state.write().unwrap();
         match command_action {
-            action::Command::RunResult(start, end, stdout, stderr, status) => {
+            action::Command::RunResult(watch, start, end, output, status) => {
+                let prev_status = state.global.latest_status(watch);
+                let prev_result = state
+                    .global
+                    .webhook
+                    .as_ref()
+                    .and_then(|_| state.global.latest_result(watch));
+                state
+                    .global
+                    .record_command_result(watch, start, end, *output, status);
                 state
                     .global
-                    .record_command_result(start, end, stdout, stderr, status);
-                state.command.running_count -= 1;
+                    .send_webhooks(watch, start, prev_status, prev_result);
+                state.global.publish(watch, start);
+                let changed = state
+                    .global
+                    .get_summary_by_start(watch, start)
+                    .is_some_and(|summary| summary.changed == Some(true));
+                let notable = status != 0 || (prev_status.is_some() && changed);
+                if notable && !state.ui.silenced {
+                    if state.global.beep {
+                        state.ui.bells += 1;
+                    }
+                    if state.global.flash {
+                        state.ui.views[watch].flashed_at = Some(Instant::now());
+                    }
+                }
+                for event in alert::transitions(prev_status, status, changed) {
+                    if state.global.notify.contains(&event) {
+                        let title =
+                            format!("boda: {}", state.global.watches[watch].command.join(" "));
+                        let body = match event {
+                            alert::Event::Fail => format!("failed with status {}", status),
+                            alert::Event::Recover => "recovered".to_string(),
+                            _ => "output changed".to_string(),
+                        };
+                        state.ui.push_notification(title, body);
+                    }
+                }
+                for alert in state.global.alerts(watch, start, prev_status) {
+                    hook::run(
+                        state.global.runner.shell(),
+                        &state.global.environment,
+                        alert,
+                    );
+                }
+                state.command.schedules[watch].running_count -= 1;
+
+                let pruned = state.global.prune();
+                for view in state.ui.views.iter_mut() {
+                    if let state::TargetCommand::Target(id) = view.target_command
+                        && pruned.contains(&id)
+                    {
+                        view.target_command = state::TargetCommand::Latest;
+                    }
+                }
+            }
+            action::Command::Chunk(watch, start, stream, chunk) => {
+                state.global.append_output(watch, start, stream, &chunk);
             }
-            action::Command::StartRun(t, start) => {
-                state.global.record_command(start);
-                state.command.prev_tick = t;
-                state.command.running_count += 1;
+            action::Command::PathsChanged(paths) => {
+                info!("changed: {:?}", paths);
+                if !state.command.paused {
+                    for schedule in state.command.schedules.iter_mut() {
+                        schedule.run_now = true;
+                    }
+                }
+            }
+            action::Command::StartRun(watch, t, start, started_tx) => {
+                let prev_status = state
+                    .global
+                    .get_history(watch)
+                    .iter()
+                    .find_map(|summary| summary.status);
+                let id = state.global.record_command(watch, start);
+
+                let schedule = &mut state.command.schedules[watch];
+                schedule.prev_tick = t;
+                schedule.run_now = false;
+                schedule.running_count += 1;
+                schedule.iteration += 1;
+                let iteration = schedule.iteration;
+
+                let started = action::Started {
+                    id,
+                    iteration,
+                    prev_status,
+                    prev_output: prev_status.map(|_| state.global.prev_output_path(watch)),
+                };
+                if let Err(e) = started_tx.send(started) {
+                    error!("error send started: {}", e);
+                }
             }
         }
     }
 }
+
+/// Move the selection by `delta` history rows, positive toward older runs.
+/// Moving before the newest row selects `Latest`.
+fn move_selection(state: &mut state::State, delta: isize) {
+    let rows = state.history_rows();
+    if rows.is_empty() {
+        return;
+    }
+
+    let current = match state.target_position() {
+        Some(position) => history::row_position(&rows, position).map_or(-1, |row| row as isize),
+        None => -1,
+    };
+    let row = current
+        .saturating_add(delta)
+        .clamp(-1, rows.len() as isize - 1);
+
+    let history = state.global.get_history(state.ui.focus);
+    state.ui.view_mut().target_command = if row < 0 {
+        state::TargetCommand::Latest
+    } else {
+        state::TargetCommand::Target(history[rows[row as usize].newest()].id)
+    };
+}
@@ -0,0 +1,124 @@
+use regex::Regex;
+
+/// A rule pulling one number out of a run's stdout.
+///
+/// Written as `[<name>=]<kind>:<arg>`, e.g. `depth=json:.queue.depth`, `re:(\d+) files`, `col:5:2`.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub name: String,
+    kind: Kind,
+}
+
+#[derive(Debug, Clone)]
+enum Kind {
+    /// First capture group, or the whole match without groups.
+    Regex(Regex),
+    /// 1-based whitespace-separated column of a 1-based line, the last line when unset.
+    Column {
+        column: usize,
+        line: Option<usize>,
+    },
+    Json(Vec<Segment>),
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+impl Rule {
+    pub fn parse(input: &str) -> Result<Rule, String> {
+        let (name, rule) = match input.split_once('=') {
+            Some((name, rule)) if !name.contains(':') => (name.trim().to_string(), rule),
+            _ => (input.to_string(), input),
+        };
+        let (kind, arg) = rule
+            .split_once(':')
+            .ok_or_else(|| format!("missing rule kind, expected re:, col: or json: in {input}"))?;
+
+        let kind = match kind {
+            "re" => Kind::Regex(Regex::new(arg).map_err(|e| e.to_string())?),
+            "col" => {
+                let (column, line) = match arg.split_once(':') {
+                    Some((column, line)) => (column, Some(parse_index(line)?)),
+                    None => (arg, None),
+                };
+                Kind::Column {
+                    column: parse_index(column)?,
+                    line,
+                }
+            }
+            "json" => Kind::Json(parse_path(arg)?),
+            _ => return Err(format!("unknown rule kind: {kind}")),
+        };
+
+        Ok(Rule { name, kind })
+    }
+
+    pub fn extract(&self, stdout: &str) -> Option<f64> {
+        match &self.kind {
+            Kind::Regex(regex) => {
+                let captures = regex.captures(stdout)?;
+                parse_number(captures.get(1).or_else(|| captures.get(0))?.as_str())
+            }
+            Kind::Column { column, line } => {
+                let line = match line {
+                    Some(line) => stdout.lines().nth(line - 1)?,
+                    None => stdout.lines().rev().find(|line| !line.trim().is_empty())?,
+                };
+                parse_number(line.split_whitespace().nth(column - 1)?)
+            }
+            Kind::Json(path) => {
+                let mut value = &serde_json::from_str::<serde_json::Value>(stdout).ok()?;
+                for segment in path {
+                    value = match segment {
+                        Segment::Key(key) => value.get(key)?,
+                        Segment::Index(index) => value.get(index)?,
+                    };
+                }
+                match value {
+                    serde_json::Value::Number(number) => number.as_f64(),
+                    serde_json::Value::String(text) => parse_number(text),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+fn parse_index(input: &str) -> Result<usize, String> {
+    match input.trim().parse::<usize>() {
+        Ok(0) | Err(_) => Err(format!("expected a 1-based index: {input}")),
+        Ok(index) => Ok(index),
+    }
+}
+
+/// Parse `.a.b[0]`, the leading dot being optional.
+fn parse_path(input: &str) -> Result<Vec<Segment>, String> {
+    let mut path = vec![];
+    for part in input.trim().trim_start_matches('.').split('.') {
+        let (key, indexes) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if !key.is_empty() {
+            path.push(Segment::Key(key.to_string()));
+        }
+        for index in indexes.split_terminator(']') {
+            let index = index
+                .strip_prefix('[')
+                .and_then(|index| index.parse().ok())
+                .ok_or_else(|| format!("invalid json path: {input}"))?;
+            path.push(Segment::Index(index));
+        }
+    }
+    Ok(path)
+}
+
+/// Lenient number parsing for values like `1,024` or `42%`.
+fn parse_number(input: &str) -> Option<f64> {
+    input
+        .trim()
+        .trim_end_matches('%')
+        .replace(',', "")
+        .parse()
+        .ok()
+}
@@ -1,3 +1,12 @@
 pub mod action;
+pub mod alert;
+pub mod blob;
+pub mod cache;
+pub mod environment;
+pub mod history;
 pub mod manager;
+pub mod metric;
+pub mod retention;
+#[allow(clippy::module_inception)]
 pub mod state;
+pub mod watch;
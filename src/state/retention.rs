@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use log::{info, warn};
+use rusqlite::{Connection, Params};
+
+use super::blob;
+
+// NOTE: rows deleted per round while shrinking toward `max_db_size`
+const PRUNE_BATCH: u32 = 16;
+
+#[derive(Debug, Clone, Default)]
+pub struct Retention {
+    pub max_history: Option<u32>,
+    pub max_age: Option<Duration>,
+    pub max_db_size: Option<u64>,
+
+    /// Never prune runs whose output changed or whose status was non-zero.
+    pub keep_notable: bool,
+}
+
+impl Retention {
+    pub fn is_enabled(&self) -> bool {
+        self.max_history.is_some() || self.max_age.is_some() || self.max_db_size.is_some()
+    }
+
+    /// Delete rows exceeding the configured limits, returning the ids that were removed.
+    /// Runs still in flight and the latest completed run of each command are never pruned.
+    pub fn prune(&self, conn: &Connection) -> rusqlite::Result<Vec<i64>> {
+        let notable = if self.keep_notable {
+            "AND status = 0 AND changed = 0"
+        } else {
+            ""
+        };
+        let prunable = format!(
+            "status IS NOT NULL {notable} AND id NOT IN (
+                SELECT MAX(id) FROM command_result WHERE status IS NOT NULL GROUP BY command_id
+            )"
+        );
+        let mut pruned = vec![];
+
+        if let Some(max_history) = self.max_history {
+            pruned.extend(delete_returning(
+                conn,
+                &format!(
+                    "DELETE FROM command_result WHERE {prunable} AND id NOT IN (
+                        SELECT id FROM (
+                            SELECT id, ROW_NUMBER() OVER (PARTITION BY command_id ORDER BY id DESC) AS n FROM command_result
+                        ) WHERE n <= ?1
+                    ) RETURNING id"
+                ),
+                [max_history],
+            )?);
+        }
+
+        if let Some(max_age) = self.max_age {
+            let threshold = chrono::Local::now().timestamp_millis() - max_age.as_millis() as i64;
+            pruned.extend(delete_returning(
+                conn,
+                &format!("DELETE FROM command_result WHERE {prunable} AND start < ?1 RETURNING id"),
+                [threshold],
+            )?);
+        }
+
+        if let Some(max_db_size) = self.max_db_size {
+            while used_size(conn)? > max_db_size {
+                let removed = delete_returning(
+                    conn,
+                    &format!(
+                        "DELETE FROM command_result WHERE id IN (SELECT id FROM command_result WHERE {prunable} ORDER BY id ASC LIMIT ?1) RETURNING id"
+                    ),
+                    [PRUNE_BATCH],
+                )?;
+                if removed.is_empty() {
+                    warn!("unable to prune below {} bytes", max_db_size);
+                    break;
+                }
+                pruned.extend(removed);
+                // NOTE: rows only reference their output, space is freed with the blobs
+                blob::prune_orphans(conn)?;
+            }
+        }
+
+        if !pruned.is_empty() {
+            info!("pruned {} runs", pruned.len());
+            blob::prune_orphans(conn)?;
+            conn.execute_batch("PRAGMA incremental_vacuum")?;
+        }
+
+        Ok(pruned)
+    }
+}
+
+fn delete_returning<P: Params>(
+    conn: &Connection,
+    sql: &str,
+    params: P,
+) -> rusqlite::Result<Vec<i64>> {
+    let mut stmt = conn.prepare(sql)?;
+    let ids = stmt.query_map(params, |row| row.get(0))?;
+
+    ids.collect()
+}
+
+fn used_size(conn: &Connection) -> rusqlite::Result<u64> {
+    let page_size: u64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    let page_count: u64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let freelist_count: u64 = conn.query_row("PRAGMA freelist_count", [], |row| row.get(0))?;
+
+    Ok((page_count - freelist_count) * page_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs of command 0: five unchanged successes then one in flight.
+    /// Runs of command 1: a failure, a change, then an unchanged success.
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE blob (hash TEXT PRIMARY KEY, data BLOB NOT NULL, compressed INTEGER NOT NULL);
+            CREATE TABLE command_result (
+                id INTEGER PRIMARY KEY,
+                command_id INTEGER NOT NULL,
+                start INTEGER NOT NULL,
+                stdout_hash TEXT,
+                stderr_hash TEXT,
+                status INTEGER,
+                changed INTEGER
+            );
+            INSERT INTO command_result (id, command_id, start, status, changed) VALUES
+                (1, 0, 1, 0, 0), (2, 0, 2, 0, 0), (3, 0, 3, 0, 0), (4, 0, 4, 0, 0), (5, 0, 5, 0, 0),
+                (6, 0, 6, NULL, NULL),
+                (7, 1, 1, 1, 1), (8, 1, 2, 0, 1), (9, 1, 3, 0, 0);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn prune(retention: Retention) -> Vec<i64> {
+        let mut pruned = retention.prune(&setup()).unwrap();
+        pruned.sort();
+        pruned
+    }
+
+    #[test]
+    fn keeps_max_history_per_command() {
+        let pruned = prune(Retention {
+            max_history: Some(2),
+            ..Retention::default()
+        });
+        assert_eq!(pruned, [1, 2, 3, 4, 7]);
+    }
+
+    #[test]
+    fn keeps_latest_completed_run() {
+        let pruned = prune(Retention {
+            max_history: Some(0),
+            ..Retention::default()
+        });
+        assert_eq!(pruned, [1, 2, 3, 4, 7, 8]);
+
+        let pruned = prune(Retention {
+            max_age: Some(Duration::from_secs(1)),
+            ..Retention::default()
+        });
+        assert_eq!(pruned, [1, 2, 3, 4, 7, 8]);
+
+        let pruned = prune(Retention {
+            max_db_size: Some(0),
+            ..Retention::default()
+        });
+        assert_eq!(pruned, [1, 2, 3, 4, 7, 8]);
+    }
+
+    #[test]
+    fn keeps_notable_runs() {
+        let pruned = prune(Retention {
+            max_history: Some(0),
+            keep_notable: true,
+            ..Retention::default()
+        });
+        assert_eq!(pruned, [1, 2, 3, 4]);
+    }
+}
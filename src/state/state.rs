@@ -1,5 +1,7 @@
 use std::{
-    fs::File,
+    collections::{BTreeMap, HashSet, VecDeque},
+    env,
+    fs::{self, File},
     path::PathBuf,
     sync::{Arc, Mutex},
     time::{Duration, Instant},
@@ -8,7 +10,16 @@ use std::{
 use log::{debug, error, info};
 use rusqlite::Connection;
 
-use crate::{Cli, util};
+use crate::{
+    Cli,
+    command::{trigger, webhook},
+    util,
+};
+
+use super::{action, alert, blob, cache, environment, history, metric, retention, watch};
+
+const MIN_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_NOTIFICATIONS: usize = 16;
 
 #[derive(Debug)]
 pub struct State {
@@ -19,33 +30,122 @@ pub struct State {
 
 impl State {
     pub fn new(cli: Cli, filepath: &PathBuf) -> State {
+        let global = Global::new(cli, filepath);
+        let ui = Ui {
+            views: vec![View::default(); global.watches.len()],
+            show_metrics: !global.metrics.is_empty(),
+            ..Ui::default()
+        };
+        let command = Command {
+            schedules: vec![Schedule::default(); global.watches.len()],
+            ..Command::default()
+        };
+
         State {
-            global: Global::new(cli, filepath),
-            ui: Ui::default(),
-            command: Command::default(),
+            global,
+            ui,
+            command,
         }
     }
 }
 
 impl State {
-    pub fn can_run(&self, t: Instant) -> bool {
-        let tick_diff = t - self.command.prev_tick;
+    pub fn history_rows(&self) -> Vec<history::Row> {
+        history::rows(
+            self.global.get_history(self.ui.focus),
+            self.ui.group_history,
+            &self.ui.view().expanded_groups,
+        )
+    }
 
-        debug!("now running: {}", self.command.running_count);
+    pub fn target_position(&self) -> Option<usize> {
+        match self.ui.view().target_command {
+            TargetCommand::Latest => None,
+            TargetCommand::Target(id) => self.global.history_position(self.ui.focus, id),
+        }
+    }
+}
+
+impl State {
+    pub fn can_run(&self, watch: usize, t: Instant) -> bool {
+        let schedule = &self.command.schedules[watch];
+        let tick_diff = t - schedule.prev_tick;
 
-        tick_diff > (self.global.interval - self.command.tick)
-            && (self.command.running_count < self.global.concurrency)
+        debug!("now running: {}", schedule.running_count);
+
+        let due = schedule.run_now
+            || (!self.command.paused
+                && self.global.watches[watch].timer
+                && tick_diff > (self.global.watches[watch].interval - self.command.tick));
+        due && (schedule.running_count < self.global.concurrency)
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Runner {
+    /// Run through `<shell> -c "<args joined by space>"`.
+    Shell(String),
+    /// Pass the argument vector straight to the program.
+    Exec,
+}
+
+impl Runner {
+    pub fn label(&self) -> String {
+        match self {
+            Runner::Shell(shell) => {
+                let name = shell.rsplit('/').next().unwrap_or(shell);
+                format!("{} -c", name)
+            }
+            Runner::Exec => "exec".to_string(),
+        }
+    }
+
+    /// Shell for hooks, which are always command lines.
+    pub fn shell(&self) -> &str {
+        match self {
+            Runner::Shell(shell) => shell,
+            Runner::Exec => "/bin/sh",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Watch {
+    pub command: Vec<String>,
+    pub interval: Duration,
+    /// Whether runs start on the interval, rather than only on `--watch-path` changes.
+    pub timer: bool,
+    pub stats: Stats,
+
+    cache: cache::Cache,
+}
+
 #[derive(Debug)]
 pub struct Global {
     pub running: bool,
 
-    pub command: Vec<String>,
-    pub interval: Duration,
+    pub watches: Vec<Watch>,
     pub concurrency: u8,
+    pub retention: retention::Retention,
+    pub compress: bool,
+    pub max_output: Option<u64>,
+    pub encoding: util::encoding::Encoding,
+    pub metrics: Vec<metric::Rule>,
+    pub hooks: alert::Hooks,
+    pub beep: bool,
+    pub flash: bool,
+    pub notify: Vec<alert::Event>,
+    pub webhook: Option<webhook::Webhook>,
+    pub watch_paths: Vec<trigger::Pattern>,
+    pub debounce: Duration,
+    pub runner: Runner,
+    pub environment: environment::Environment,
+
+    /// Receivers of finished runs, e.g. `--serve` event streams.
+    subscribers: Mutex<Vec<crossbeam_channel::Sender<(usize, CommandResultSummary)>>>,
 
+    // NOTE: scratch files handed to the command, removed on exit
+    rundir: PathBuf,
     conn: Arc<Mutex<Connection>>,
 }
 
@@ -55,31 +155,162 @@ impl Global {
         info!("db file at {:?}", filepath);
         let conn = Connection::open(filepath).unwrap();
 
+        // NOTE: must be set before any table is created
+        conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL")
+            .unwrap();
+        conn.execute(
+            "CREATE TABLE blob (
+                hash TEXT PRIMARY KEY,
+                data BLOB NOT NULL,
+                compressed INTEGER NOT NULL
+            )",
+            (),
+        )
+        .unwrap();
+        conn.execute(
+            "CREATE TABLE metric (
+                result_id INTEGER NOT NULL REFERENCES command_result(id) ON DELETE CASCADE,
+                name TEXT NOT NULL,
+                value REAL NOT NULL
+            )",
+            (),
+        )
+        .unwrap();
+        conn.execute(
+            "CREATE TABLE command (
+                id INTEGER PRIMARY KEY,
+                command TEXT NOT NULL,
+                interval INTEGER NOT NULL
+            )",
+            (),
+        )
+        .unwrap();
         conn.execute(
             "CREATE TABLE command_result (
                 id INTEGER PRIMARY KEY,
+                command_id INTEGER NOT NULL REFERENCES command(id),
                 start INTEGER NOT NULL,
                 end INTEGER,
-                stdout TEXT,
-                stderr TEXT,
-                status INTEGER
+                stdout_hash TEXT REFERENCES blob(hash),
+                stderr_hash TEXT REFERENCES blob(hash),
+                stdout_size INTEGER,
+                stderr_size INTEGER,
+                status INTEGER,
+                changed INTEGER,
+                user_time INTEGER,
+                system_time INTEGER,
+                max_rss INTEGER,
+                read_bytes INTEGER,
+                write_bytes INTEGER,
+                stdout_lines INTEGER,
+                stderr_lines INTEGER,
+                signal INTEGER
             )",
             (),
         )
         .unwrap();
 
-        let interval = if cli.interval < 0.5 {
-            Duration::from_millis(500)
+        let interval = Duration::try_from_secs_f64(cli.interval.unwrap_or(1.0).max(0.0))
+            .unwrap_or(Duration::MAX)
+            .max(MIN_INTERVAL);
+        // NOTE: with `--watch-path`, only an interval given explicitly keeps the timer
+        let timer = cli.watch_path.is_empty() || cli.interval.is_some();
+
+        let rundir = filepath.with_extension("run");
+        fs::create_dir_all(&rundir).unwrap();
+
+        let runner = if cli.exec {
+            Runner::Exec
         } else {
-            Duration::from_secs_f64(cli.interval)
+            Runner::Shell(
+                cli.shell
+                    .or_else(|| env::var("SHELL").ok())
+                    .unwrap_or_else(|| "/bin/sh".to_string()),
+            )
         };
 
+        let mut specs = vec![];
+        if !cli.command.is_empty() || cli.cmd.is_empty() {
+            specs.push(watch::Spec {
+                command: cli.command,
+                interval: None,
+            });
+        }
+        specs.extend(cli.cmd.into_iter().map(|spec| match runner {
+            // NOTE: `--cmd` takes a single string, split it into words for exec
+            Runner::Exec => watch::Spec {
+                command: watch::split_words(&spec.command.join(" ")),
+                ..spec
+            },
+            Runner::Shell(_) => spec,
+        }));
+
+        let watches = specs
+            .into_iter()
+            .enumerate()
+            .map(|(id, spec)| {
+                let interval = spec.interval.map_or(interval, |it| it.max(MIN_INTERVAL));
+                conn.execute(
+                    "INSERT INTO command (id, command, interval) VALUES (?1, ?2, ?3)",
+                    (id, spec.command.join(" "), interval.as_millis() as i64),
+                )
+                .unwrap();
+
+                Watch {
+                    command: spec.command,
+                    interval,
+                    timer: timer || spec.interval.is_some(),
+                    stats: Stats::default(),
+                    cache: cache::Cache::default(),
+                }
+            })
+            .collect();
+
         Global {
             running: true,
 
-            command: cli.command,
-            interval,
+            watches,
             concurrency: cli.concurrency,
+            retention: retention::Retention {
+                max_history: cli.max_history,
+                max_age: cli.max_age,
+                max_db_size: cli.max_db_size,
+                keep_notable: cli.keep_notable,
+            },
+            compress: cli.compress,
+            max_output: cli.max_output,
+            encoding: cli.encoding,
+            metrics: cli.metric,
+            beep: cli.beep,
+            flash: cli.flash,
+            notify: cli.notify,
+            webhook: cli
+                .webhook
+                .map(|url| webhook::Webhook::spawn(url, cli.webhook_on, cli.encoding)),
+            watch_paths: cli.watch_path,
+            debounce: cli.debounce,
+            hooks: alert::Hooks {
+                on_change: cli.on_change,
+                on_fail: cli.on_fail,
+                on_recover: cli.on_recover,
+                // NOTE: the regex was validated in `main`
+                on_match: cli
+                    .on_match
+                    .map(|args| (regex::Regex::new(&args[0]).unwrap(), args[1].clone())),
+            },
+            runner,
+            environment: environment::Environment {
+                cwd: cli.cwd,
+                vars: cli.env,
+                stdin: match (cli.stdin_file, cli.stdin) {
+                    (Some(path), _) => Some(environment::Stdin::File(path)),
+                    (None, Some(text)) => Some(environment::Stdin::Text(text)),
+                    (None, None) => None,
+                },
+            },
+
+            subscribers: Mutex::new(vec![]),
+            rundir,
 
             conn: Arc::new(Mutex::new(conn)),
         }
@@ -87,220 +318,768 @@ impl Global {
 }
 
 impl Global {
-    pub fn record_command(&self, start: chrono::DateTime<chrono::Local>) {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT INTO command_result (start) VALUES (?1)",
-            (start.timestamp_millis(),),
-        )
-        .unwrap();
+    pub fn record_command(&mut self, watch: usize, start: chrono::DateTime<chrono::Local>) -> i64 {
+        let id = {
+            let conn = self.conn.lock().unwrap();
+            conn.execute(
+                "INSERT INTO command_result (command_id, start) VALUES (?1, ?2)",
+                (watch, start.timestamp_millis()),
+            )
+            .unwrap();
+            conn.last_insert_rowid()
+        };
+
+        self.watches[watch]
+            .cache
+            .push_summary(CommandResultSummary {
+                id,
+                start,
+                end: None,
+                status: None,
+                stdout_hash: None,
+                stderr_hash: None,
+                changed: None,
+                truncated: false,
+                usage: None,
+                stdout_size: None,
+                stderr_size: None,
+                stdout_lines: None,
+                stderr_lines: None,
+                signal: None,
+                metrics: vec![],
+            });
+
+        id
     }
 
     pub fn record_command_result(
-        &self,
+        &mut self,
+        watch: usize,
         start: util::chrono::DateTime,
         end: util::chrono::DateTime,
-        stdout: String,
-        stderr: String,
+        output: action::RunOutput,
         status: u8,
     ) {
+        let action::RunOutput {
+            stdout,
+            stderr,
+            stdout_size,
+            stderr_size,
+            usage,
+            signal,
+            blobs,
+        } = output;
+        let (stdout_blob, stderr_blob) = blobs.unwrap_or_else(|| {
+            (
+                blob::Blob::new(&stdout, self.compress),
+                blob::Blob::new(&stderr, self.compress),
+            )
+        });
+        let (stdout_lines, stderr_lines) = (count_lines(&stdout), count_lines(&stderr));
+        let metrics = if self.metrics.is_empty() {
+            vec![]
+        } else {
+            let text = self.encoding.decode(&stdout);
+            self.metrics
+                .iter()
+                .map(|rule| rule.extract(&text))
+                .collect::<Vec<_>>()
+        };
+        {
+            let conn = self.conn.lock().unwrap();
+            blob::store(&conn, &stdout_blob).unwrap();
+            blob::store(&conn, &stderr_blob).unwrap();
+        }
+        let (stdout_hash, stderr_hash) = (stdout_blob.hash, stderr_blob.hash);
+
+        let changed = match self.watches[watch].cache.latest_completed() {
+            Some(prev) => {
+                prev.status != Some(status)
+                    || prev.stdout_hash.as_ref() != Some(&stdout_hash)
+                    || prev.stderr_hash.as_ref() != Some(&stderr_hash)
+            }
+            None => true,
+        };
+        self.watches[watch].stats.record(
+            status,
+            changed,
+            (end - start).to_std().unwrap_or_default(),
+        );
+        let cache = &mut self.watches[watch].cache;
+
+        {
+            let conn = self.conn.lock().unwrap();
+            conn.execute(
+                "UPDATE command_result SET stdout_hash=?1, stderr_hash=?2, stdout_size=?3, stderr_size=?4, status=?5, end=?6, changed=?7, stdout_lines=?8, stderr_lines=?9, signal=?10 WHERE command_id=?11 AND start=?12",
+                (
+                    &stdout_hash,
+                    &stderr_hash,
+                    stdout_size,
+                    stderr_size,
+                    status,
+                    end.timestamp_millis(),
+                    changed,
+                    stdout_lines,
+                    stderr_lines,
+                    signal,
+                    watch,
+                    start.timestamp_millis(),
+                ),
+            )
+            .unwrap();
+            if let Some(usage) = usage {
+                conn.execute(
+                    "UPDATE command_result SET user_time=?1, system_time=?2, max_rss=?3, read_bytes=?4, write_bytes=?5 WHERE command_id=?6 AND start=?7",
+                    (
+                        usage.user_time.as_micros() as i64,
+                        usage.system_time.as_micros() as i64,
+                        usage.max_rss,
+                        usage.read_bytes,
+                        usage.write_bytes,
+                        watch,
+                        start.timestamp_millis(),
+                    ),
+                )
+                .unwrap();
+            }
+            for (rule, value) in self.metrics.iter().zip(&metrics) {
+                if let Some(value) = value {
+                    conn.execute(
+                        "INSERT INTO metric (result_id, name, value) SELECT id, ?1, ?2 FROM command_result WHERE command_id=?3 AND start=?4",
+                        (&rule.name, value, watch, start.timestamp_millis()),
+                    )
+                    .unwrap();
+                }
+            }
+        }
+
+        if let Some(summary) = cache.summary_by_start_mut(start) {
+            let id = summary.id;
+            summary.end = Some(end);
+            summary.status = Some(status);
+            summary.stdout_hash = Some(stdout_hash);
+            summary.stderr_hash = Some(stderr_hash);
+            summary.changed = Some(changed);
+            summary.usage = usage;
+            summary.stdout_size = Some(stdout_size);
+            summary.stderr_size = Some(stderr_size);
+            summary.stdout_lines = Some(stdout_lines);
+            summary.stderr_lines = Some(stderr_lines);
+            summary.signal = signal;
+            summary.metrics = metrics;
+            summary.truncated =
+                stdout_size > stdout.len() as u64 || stderr_size > stderr.len() as u64;
+
+            cache.remove_partial(id);
+            cache.put_result(Arc::new(CommandResult {
+                id,
+                start,
+                stdout: Some(stdout),
+                stderr: Some(stderr),
+                stdout_size: Some(stdout_size),
+                stderr_size: Some(stderr_size),
+                status: Some(status),
+            }));
+        }
+    }
+
+    /// Run `watch` every `interval`, turning the timer on for a watch that only ran on `--watch-path` changes.
+    pub fn set_interval(&mut self, watch: usize, interval: Duration) {
+        let interval = interval.max(MIN_INTERVAL);
+        self.watches[watch].interval = interval;
+        self.watches[watch].timer = true;
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "UPDATE command_result SET stdout=?1, stderr=?2, status=?3, end=?4 WHERE start=?5",
-            (
-                stdout,
-                stderr,
-                status,
-                end.timestamp_millis(),
-                start.timestamp_millis(),
-            ),
+            "UPDATE command SET interval=?1 WHERE id=?2",
+            (interval.as_millis() as i64, watch),
         )
         .unwrap();
     }
 
-    pub fn get_target_command_result(
+    pub fn get_summary_by_start(
+        &self,
+        watch: usize,
+        start: util::chrono::DateTime,
+    ) -> Option<&CommandResultSummary> {
+        self.watches[watch]
+            .cache
+            .summaries()
+            .iter()
+            .find(|summary| summary.start == start)
+    }
+
+    /// Watch whose history holds run `id`.
+    pub fn find_watch(&self, id: i64) -> Option<usize> {
+        self.watches
+            .iter()
+            .position(|watch| watch.cache.summary(id).is_some())
+    }
+
+    pub fn subscribe(&self) -> crossbeam_channel::Receiver<(usize, CommandResultSummary)> {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Hand the finished run of `watch` started at `start` to every subscriber still listening.
+    pub fn publish(&self, watch: usize, start: util::chrono::DateTime) {
+        let Some(summary) = self.get_summary_by_start(watch, start) else {
+            return;
+        };
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send((watch, summary.clone())).is_ok());
+    }
+
+    pub fn latest_status(&self, watch: usize) -> Option<u8> {
+        self.watches[watch]
+            .cache
+            .latest_completed()
+            .and_then(|summary| summary.status)
+    }
+
+    pub fn latest_result(&self, watch: usize) -> Option<Arc<CommandResult>> {
+        let id = self.watches[watch].cache.latest_completed()?.id;
+        self.get_target_command_result(watch, &TargetCommand::Target(id))
+    }
+
+    /// Queue `--webhook` payloads for the run of `watch` started at `start`,
+    /// which followed one with `prev_status` and `prev_result`.
+    pub fn send_webhooks(
+        &self,
+        watch: usize,
+        start: util::chrono::DateTime,
+        prev_status: Option<u8>,
+        prev_result: Option<Arc<CommandResult>>,
+    ) {
+        let Some(webhook) = &self.webhook else {
+            return;
+        };
+        let Some(summary) = self.get_summary_by_start(watch, start) else {
+            return;
+        };
+        let (Some(status), Some(end)) = (summary.status, summary.end) else {
+            return;
+        };
+        let events = alert::transitions(prev_status, status, summary.changed == Some(true))
+            .into_iter()
+            .filter(|event| webhook.events.contains(event))
+            .collect::<Vec<_>>();
+        if events.is_empty() {
+            return;
+        }
+
+        // NOTE: decoding and diffing the output is left to the webhook thread
+        webhook.send(webhook::Run {
+            events,
+            status,
+            result: self.get_target_command_result(watch, &TargetCommand::Target(summary.id)),
+            prev_result,
+            metadata: serde_json::json!({
+                "command": self.watches[watch].command.join(" "),
+                "run_id": summary.id,
+                "status": status,
+                "signal": summary.signal,
+                "start": summary.start.to_rfc3339(),
+                "end": end.to_rfc3339(),
+                "duration_ms": (end - summary.start).num_milliseconds(),
+                "truncated": summary.truncated,
+            }),
+        });
+    }
+
+    /// Hooks to run for the run of `watch` started at `start`, which followed one with `prev_status`.
+    pub fn alerts(
+        &self,
+        watch: usize,
+        start: util::chrono::DateTime,
+        prev_status: Option<u8>,
+    ) -> Vec<alert::Alert> {
+        if self.hooks.is_empty() {
+            return vec![];
+        }
+        let Some(summary) = self.get_summary_by_start(watch, start).cloned() else {
+            return vec![];
+        };
+        let (Some(status), Some(end)) = (summary.status, summary.end) else {
+            return vec![];
+        };
+        let stdout = self
+            .get_target_command_result(watch, &TargetCommand::Target(summary.id))
+            .and_then(|result| result.stdout.clone())
+            .unwrap_or_default();
+        let text = self.encoding.decode(&stdout);
+
+        let mut vars = vec![
+            ("BODA_RUN_ID", summary.id.to_string()),
+            ("BODA_COMMAND", self.watches[watch].command.join(" ")),
+            ("BODA_STATUS", status.to_string()),
+            ("BODA_START", summary.start.to_rfc3339()),
+            ("BODA_END", end.to_rfc3339()),
+            (
+                "BODA_DURATION_MS",
+                (end - summary.start).num_milliseconds().to_string(),
+            ),
+        ];
+        if let Some(prev_status) = prev_status {
+            vars.push(("BODA_PREV_STATUS", prev_status.to_string()));
+        }
+        if let Some(signal) = summary.signal {
+            vars.push(("BODA_SIGNAL", signal.to_string()));
+        }
+
+        self.hooks
+            .events(prev_status, status, summary.changed == Some(true), &text)
+            .into_iter()
+            .map(|(event, hook)| alert::Alert {
+                event,
+                hook: hook.to_string(),
+                vars: vars
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.clone()))
+                    .chain([("BODA_EVENT".to_string(), event.name().to_string())])
+                    .collect(),
+                stdin: stdout.clone(),
+            })
+            .collect()
+    }
+
+    pub fn get_target_summary(
         &self,
+        watch: usize,
         target_command: &TargetCommand,
-    ) -> Option<CommandResult> {
+    ) -> Option<&CommandResultSummary> {
+        let cache = &self.watches[watch].cache;
         match target_command {
-            TargetCommand::Latest => self.last_command_result(),
-            TargetCommand::Target(id) => self.get_command_result(*id),
+            TargetCommand::Latest => cache.latest_visible(),
+            TargetCommand::Target(id) => cache.summary(*id),
         }
     }
 
-    fn last_command_result(&self) -> Option<CommandResult> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = match conn.prepare(
-            "SELECT start, stdout, stderr, status FROM command_result WHERE status IS NOT NULL ORDER BY id DESC LIMIT 1",
-        ) {
-            Ok(stmt) => stmt,
-            Err(e) => {
-                error!("error on select: {}", e);
-                return None;
-            }
+    pub fn get_target_command_result(
+        &self,
+        watch: usize,
+        target_command: &TargetCommand,
+    ) -> Option<Arc<CommandResult>> {
+        let cache = &self.watches[watch].cache;
+        let id = match target_command {
+            TargetCommand::Latest => cache.latest_visible()?.id,
+            TargetCommand::Target(id) => *id,
         };
-        let result_iter = stmt
-            .query_map([], |row| {
-                Ok(CommandResult {
-                    start: chrono::DateTime::from_timestamp_millis(row.get(0).unwrap())
-                        .unwrap()
-                        .into(),
-                    stdout: row.get(1).unwrap(),
-                    stderr: row.get(2).unwrap(),
-                    status: row.get(3).unwrap(),
-                })
-            })
-            .unwrap();
 
-        for result in result_iter {
-            if let Ok(result) = result {
-                return Some(result);
-            }
+        if let Some(partial) = cache.partial(id) {
+            return Some(partial);
         }
-        None
+        if let Some(result) = cache.get_result(id) {
+            return Some(result);
+        }
+        if let Some(summary) = cache.summary(id)
+            && summary.status.is_none()
+        {
+            return None;
+        }
+
+        let result = Arc::new(self.get_command_result(id)?);
+        cache.put_result(result.clone());
+        Some(result)
     }
 
-    fn get_command_result(&self, id: u16) -> Option<CommandResult> {
+    fn get_command_result(&self, id: i64) -> Option<CommandResult> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = match conn.prepare(
-            "SELECT start, stdout, stderr, status FROM command_result WHERE status IS NOT NULL AND id=?1 ORDER BY id DESC LIMIT 1",
-        ) {
-            Ok(stmt) => stmt,
+        let row = conn.query_row(
+            "SELECT id, start, stdout_hash, stderr_hash, status, stdout_size, stderr_size FROM command_result WHERE status IS NOT NULL AND id=?1",
+            [id],
+            |row| {
+                Ok((
+                    row.get::<usize, i64>(0)?,
+                    row.get::<usize, i64>(1)?,
+                    row.get::<usize, Option<String>>(2)?,
+                    row.get::<usize, Option<String>>(3)?,
+                    row.get::<usize, Option<u8>>(4)?,
+                    row.get::<usize, Option<u64>>(5)?,
+                    row.get::<usize, Option<u64>>(6)?,
+                ))
+            },
+        );
+        let (id, start, stdout_hash, stderr_hash, status, stdout_size, stderr_size) = match row {
+            Ok(row) => row,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return None,
             Err(e) => {
                 error!("error on select: {}", e);
                 return None;
             }
         };
-        let result_iter = stmt
-            .query_map([id], |row| {
-                Ok(CommandResult {
-                    start: chrono::DateTime::from_timestamp_millis(row.get(0).unwrap())
-                        .unwrap()
-                        .into(),
-                    stdout: row.get(1).unwrap(),
-                    stderr: row.get(2).unwrap(),
-                    status: row.get(3).unwrap(),
-                })
-            })
-            .unwrap();
 
-        for result in result_iter {
-            if let Ok(result) = result {
-                return Some(result);
-            }
-        }
-        None
+        let load = |hash: Option<String>| blob::load(&conn, &hash?).ok()?;
+
+        Some(CommandResult {
+            id,
+            start: chrono::DateTime::from_timestamp_millis(start)
+                .unwrap()
+                .into(),
+            stdout: load(stdout_hash),
+            stderr: load(stderr_hash),
+            stdout_size,
+            stderr_size,
+            status,
+        })
     }
 
-    pub fn get_history(&self) -> Vec<CommandResultSummary> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = match conn
-            .prepare("SELECT id, start, end, status FROM command_result ORDER BY id DESC")
+    pub fn append_output(
+        &mut self,
+        watch: usize,
+        start: util::chrono::DateTime,
+        stream: action::Stream,
+        chunk: &[u8],
+    ) {
+        let cache = &mut self.watches[watch].cache;
+        if let Some(summary) = cache.summary_by_start_mut(start)
+            && summary.status.is_none()
         {
-            Ok(stmt) => stmt,
-            Err(e) => {
-                error!("error on select: {}", e);
-                return vec![];
+            let id = summary.id;
+            cache.append_partial(id, start, stream, chunk);
+        }
+    }
+
+    pub fn prev_output_path(&self, watch: usize) -> PathBuf {
+        self.rundir.join(format!("{}.prev", watch))
+    }
+
+    pub fn cleanup(&self) {
+        if let Err(e) = fs::remove_dir_all(&self.rundir) {
+            error!("error on cleanup: {}", e);
+        }
+    }
+
+    pub fn get_history(&self, watch: usize) -> &VecDeque<CommandResultSummary> {
+        self.watches[watch].cache.summaries()
+    }
+
+    pub fn history_position(&self, watch: usize, id: i64) -> Option<usize> {
+        self.get_history(watch)
+            .iter()
+            .position(|summary| summary.id == id)
+    }
+
+    pub fn prune(&mut self) -> Vec<i64> {
+        if !self.retention.is_enabled() {
+            return vec![];
+        }
+
+        let pruned = {
+            let conn = self.conn.lock().unwrap();
+            match self.retention.prune(&conn) {
+                Ok(pruned) => pruned,
+                Err(e) => {
+                    error!("error on prune: {}", e);
+                    return vec![];
+                }
             }
         };
-        let result_iter = stmt
-            .query_map([], |row| {
-                Ok(CommandResultSummary {
-                    id: row.get(0).unwrap(),
-                    start: chrono::DateTime::from_timestamp_millis(row.get(1).unwrap())
-                        .unwrap()
-                        .into(),
-                    end: match row.get::<usize, i64>(2) {
-                        Ok(r) => Some(chrono::DateTime::from_timestamp_millis(r).unwrap().into()),
-                        Err(_) => None,
-                    },
-                    status: row.get(3).unwrap(),
-                })
-            })
-            .unwrap();
+        for watch in self.watches.iter_mut() {
+            watch.cache.remove(&pruned);
+        }
 
-        return result_iter.map(|it| it.unwrap()).collect();
+        pruned
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct CommandResult {
+    pub id: i64,
     pub start: util::chrono::DateTime,
 
-    pub stdout: Option<String>,
-    pub stderr: Option<String>,
+    pub stdout: Option<Vec<u8>>,
+    pub stderr: Option<Vec<u8>>,
+    /// Size before `--max-output` truncation, `None` while in flight.
+    pub stdout_size: Option<u64>,
+    pub stderr_size: Option<u64>,
     pub status: Option<u8>,
 }
 
 impl CommandResult {
-    fn lines(input: &Option<String>) -> Vec<String> {
-        if let Some(input) = input {
-            input.lines().map(|line| line.to_string()).collect()
-        } else {
-            vec![]
+    /// Display lines of `input`, as a hex dump when forced by `hex` or when it looks binary.
+    fn lines(
+        input: &Option<Vec<u8>>,
+        encoding: &util::encoding::Encoding,
+        hex: bool,
+    ) -> Vec<String> {
+        match input.as_deref() {
+            Some(input) if hex || util::encoding::is_binary(input) => {
+                util::encoding::hex_dump(input)
+            }
+            Some(input) => encoding
+                .decode(input)
+                .lines()
+                .map(|line| line.to_string())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Number of lines `lines` returns, counted without building them.
+    fn count(input: &Option<Vec<u8>>, encoding: &util::encoding::Encoding, hex: bool) -> usize {
+        match input.as_deref() {
+            Some(input) if hex || util::encoding::is_binary(input) => {
+                util::encoding::hex_dump_len(input.len())
+            }
+            Some(input) => encoding.count_lines(input),
+            None => 0,
         }
     }
 
-    pub fn get_content(&self) -> Vec<String> {
+    pub fn has_output(&self) -> bool {
+        [&self.stdout, &self.stderr]
+            .iter()
+            .any(|output| output.as_deref().is_some_and(|it| !it.is_empty()))
+    }
+
+    /// Whether the shown output is rendered as a hex dump.
+    pub fn is_binary(&self) -> bool {
+        let output = match self.status {
+            Some(0) => &self.stdout,
+            Some(_) => &self.stderr,
+            None => return false,
+        };
+        output.as_deref().is_some_and(util::encoding::is_binary)
+    }
+
+    /// Marker for a shown output cut by `--max-output`.
+    fn truncation(output: &Option<Vec<u8>>, size: Option<u64>) -> Option<String> {
+        let kept = output.as_deref().map_or(0, <[u8]>::len) as u64;
+        let size = size?;
+
+        (size > kept).then(|| {
+            format!(
+                "[boda: output truncated, showing {} of {}]",
+                util::size::format_size(kept),
+                util::size::format_size(size)
+            )
+        })
+    }
+
+    /// Number of lines `get_content` returns, counted without decoding the output.
+    pub fn content_len(&self, encoding: &util::encoding::Encoding, hex: bool) -> usize {
         match self.status {
             Some(status) => {
-                if status == 0 {
-                    CommandResult::lines(&self.stdout)
+                let (output, size) = if status == 0 {
+                    (&self.stdout, self.stdout_size)
                 } else {
-                    CommandResult::lines(&self.stderr)
-                }
+                    (&self.stderr, self.stderr_size)
+                };
+                CommandResult::count(output, encoding, hex)
+                    + usize::from(CommandResult::truncation(output, size).is_some())
+            }
+            None if self.has_output() => {
+                CommandResult::count(&self.stdout, encoding, hex)
+                    + CommandResult::count(&self.stderr, encoding, hex)
+            }
+            None => 1,
+        }
+    }
+
+    pub fn get_content(&self, encoding: &util::encoding::Encoding, hex: bool) -> Vec<String> {
+        match self.status {
+            Some(status) => {
+                let (output, size) = if status == 0 {
+                    (&self.stdout, self.stdout_size)
+                } else {
+                    (&self.stderr, self.stderr_size)
+                };
+                let mut lines = CommandResult::lines(output, encoding, hex);
+                lines.extend(CommandResult::truncation(output, size));
+                lines
+            }
+            // NOTE: still in flight, show whatever was streamed so far
+            None if self.has_output() => {
+                let mut lines = CommandResult::lines(&self.stdout, encoding, hex);
+                lines.extend(CommandResult::lines(&self.stderr, encoding, hex));
+                lines
             }
             None => vec!["Running".to_string()],
         }
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct CommandResultSummary {
-    pub id: u16,
+    pub id: i64,
     pub start: util::chrono::DateTime,
     pub end: Option<util::chrono::DateTime>,
     pub status: Option<u8>,
+
+    pub stdout_hash: Option<String>,
+    pub stderr_hash: Option<String>,
+    /// Whether output or status differs from the previously completed run.
+    pub changed: Option<bool>,
+    /// Whether stdout or stderr was cut by `--max-output`.
+    pub truncated: bool,
+    pub usage: Option<action::Usage>,
+
+    /// Size before `--max-output` truncation, `None` while in flight.
+    pub stdout_size: Option<u64>,
+    pub stderr_size: Option<u64>,
+    /// Lines in the kept output.
+    pub stdout_lines: Option<u64>,
+    pub stderr_lines: Option<u64>,
+    pub signal: Option<u8>,
+    /// Value of each `--metric` rule, `None` when it did not match.
+    pub metrics: Vec<Option<f64>>,
+}
+
+/// Number of lines in `output`, counting a last line without a trailing newline.
+fn count_lines(output: &[u8]) -> u64 {
+    let newlines = output.iter().filter(|b| **b == b'\n').count() as u64;
+    match output.last() {
+        Some(b'\n') | None => newlines,
+        Some(_) => newlines + 1,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Layout {
+    #[default]
+    Tiles,
+    Tabs,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct Ui {
     pub show_history: bool,
 
+    pub show_help: bool,
+
+    pub show_details: bool,
+
+    pub show_chart: bool,
+
+    pub show_metrics: bool,
+
+    /// Suppress `--beep` and `--flash`.
+    pub silenced: bool,
+    /// Bumped for every `--beep`, the UI rings when it differs from the last seen value.
+    pub bells: u64,
+    /// Latest `--notify` notifications, the UI sends those newer than the last it sent.
+    pub notifications: VecDeque<Notification>,
+
+    pub group_history: bool,
+
+    pub layout: Layout,
+    pub focus: usize,
+    pub views: Vec<View>,
+}
+
+impl Ui {
+    pub fn push_notification(&mut self, title: String, body: String) {
+        let seq = self.notifications.back().map_or(1, |last| last.seq + 1);
+        self.notifications
+            .push_back(Notification { seq, title, body });
+        // NOTE: the UI drains every frame, older entries are only kept for `seq`
+        while self.notifications.len() > MAX_NOTIFICATIONS {
+            self.notifications.pop_front();
+        }
+    }
+
+    pub fn view(&self) -> &View {
+        &self.views[self.focus]
+    }
+
+    pub fn view_mut(&mut self) -> &mut View {
+        &mut self.views[self.focus]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub seq: u64,
+    pub title: String,
+    pub body: String,
+}
+
+/// Per-command view state.
+#[derive(Debug, Clone, Default)]
+pub struct View {
     pub vertical_scroll: u16,
 
-    pub show_help: bool,
     pub target_command: TargetCommand,
+
+    pub expanded_groups: HashSet<i64>,
+
+    /// Show the output as a hex dump even when it looks like text.
+    pub hex: bool,
+
+    /// Last `--flash`, the header border is highlighted for a moment after it.
+    pub flashed_at: Option<Instant>,
 }
 
 #[derive(Debug, Clone, Default)]
 pub enum TargetCommand {
     #[default]
     Latest,
-    Target(u16),
+    Target(i64),
 }
 
-impl TargetCommand {
-    pub fn is_target(&self, summary: &CommandResultSummary) -> bool {
-        match self {
-            TargetCommand::Latest => false,
-            TargetCommand::Target(id) => *id == summary.id,
+#[derive(Debug, Clone)]
+pub struct Command {
+    pub tick: Duration,
+    pub schedules: Vec<Schedule>,
+    /// Runs only start on demand while paused.
+    pub paused: bool,
+}
+
+impl Default for Command {
+    fn default() -> Self {
+        Command {
+            tick: Duration::from_millis(10),
+            schedules: vec![],
+            paused: false,
+        }
+    }
+}
+
+/// Counters over every run of a watch, kept when runs are pruned.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    pub runs: u64,
+    /// Failed runs by exit status.
+    pub failures: BTreeMap<u8, u64>,
+    /// Runs whose output or status differs from the previous run, the first run excluded.
+    pub changes: u64,
+    pub last_status: Option<u8>,
+    pub last_duration: Option<Duration>,
+}
+
+impl Stats {
+    fn record(&mut self, status: u8, changed: bool, duration: Duration) {
+        if status != 0 {
+            *self.failures.entry(status).or_default() += 1;
         }
+        if changed && self.runs > 0 {
+            self.changes += 1;
+        }
+        self.runs += 1;
+        self.last_status = Some(status);
+        self.last_duration = Some(duration);
     }
 }
 
 #[derive(Debug, Clone)]
-pub struct Command {
-    pub tick: Duration,
+pub struct Schedule {
     pub prev_tick: Instant,
     pub running_count: u8,
+    pub iteration: u64,
+    /// Run on the next tick regardless of the interval.
+    pub run_now: bool,
 }
 
-impl Default for Command {
+impl Default for Schedule {
     fn default() -> Self {
-        Command {
-            tick: Duration::from_millis(10),
+        Schedule {
             prev_tick: Instant::now(),
             running_count: 0u8,
+            iteration: 0,
+            run_now: false,
         }
     }
 }
@@ -0,0 +1,78 @@
+use std::{fs, path::Path, time::Duration};
+
+use crate::{error::BodaResult, util};
+
+/// A command to watch, optionally with its own interval.
+///
+/// Written as `<command>` or `@<interval> <command>`, e.g. `@5s df -h`.
+#[derive(Debug, Clone)]
+pub struct Spec {
+    pub command: Vec<String>,
+    pub interval: Option<Duration>,
+}
+
+impl Spec {
+    pub fn parse(input: &str) -> Result<Spec, String> {
+        let input = input.trim();
+
+        let (interval, command) = match input.strip_prefix('@') {
+            Some(rest) => {
+                let (interval, command) = rest
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| format!("missing command after interval: {input}"))?;
+                (
+                    Some(util::chrono::parse_duration(interval)?),
+                    command.trim(),
+                )
+            }
+            None => (None, input),
+        };
+        if command.is_empty() {
+            return Err("empty command".to_string());
+        }
+
+        Ok(Spec {
+            command: vec![command.to_string()],
+            interval,
+        })
+    }
+
+    /// Read one spec per line, skipping blank lines and `#` comments.
+    pub fn load_profile(path: &Path) -> BodaResult<Vec<Spec>> {
+        fs::read_to_string(path)?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| Spec::parse(line).map_err(|e| format!("{}: {}", path.display(), e).into()))
+            .collect()
+    }
+}
+
+/// Split a command line into words, honoring single quotes, double quotes and backslash escapes.
+pub fn split_words(input: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                if let Some(escaped) = chars.next() {
+                    word.get_or_insert_with(String::new).push(escaped);
+                }
+            }
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+
+    words
+}
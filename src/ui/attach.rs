@@ -0,0 +1,399 @@
+use std::{
+    cell::RefCell,
+    env, fs,
+    io::{self, BufRead, BufReader, Write},
+    iter, mem,
+    net::Shutdown,
+    os::unix::{
+        fs::PermissionsExt,
+        io::AsRawFd,
+        net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+    rc::Rc,
+    sync::{Arc, RwLock},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crossbeam_channel::{Receiver, TryRecvError, unbounded};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use log::{debug, error, info, warn};
+use ratatui::{Terminal, TerminalOptions, Viewport, backend::CrosstermBackend, layout::Rect};
+
+use crate::{error::BodaResult, state};
+
+use super::{control, manager};
+
+const FRAME: Duration = Duration::from_millis(100);
+// NOTE: a viewer not reading for this long, e.g. a suspended `boda attach`, is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+const PREFIX: &str = "boda-";
+const SUFFIX: &str = ".sock";
+
+/// Unix socket of a session, serving the UI to `boda attach` viewers and taking `boda send` commands.
+///
+/// Viewers share the state of the original terminal, like `tmux attach`: moving the selection in one moves it in all of them.
+pub struct Server {
+    listener: UnixListener,
+}
+
+/// Frames drawn for a viewer, sent once the state lock is released.
+#[derive(Debug, Clone, Default)]
+struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+impl Buffer {
+    fn take(&self) -> Vec<u8> {
+        mem::take(&mut self.0.borrow_mut())
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Input of a viewer, one line each.
+#[derive(Debug)]
+enum Message {
+    Size(u16, u16),
+    Key(KeyEvent),
+}
+
+pub fn socket_path(session: &str) -> PathBuf {
+    env::temp_dir().join(format!("{}{}{}", PREFIX, session, SUFFIX))
+}
+
+impl Server {
+    pub fn new(session: &str) -> BodaResult<Server> {
+        let path = socket_path(session);
+        let listener =
+            UnixListener::bind(&path).map_err(|e| format!("cannot listen on {:?}: {}", path, e))?;
+        // NOTE: the temp dir is shared, only the user running boda may attach or send commands
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        info!("session {} at {:?}", session, path);
+        Ok(Server { listener })
+    }
+
+    pub fn run(
+        self,
+        state: Arc<RwLock<state::state::State>>,
+        ui: &manager::Manager,
+    ) -> JoinHandle<()> {
+        let ui = ui.attached();
+        thread::spawn(move || {
+            for stream in self.listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let state = state.clone();
+                        let ui = ui.attached();
+                        thread::spawn(move || {
+                            if let Err(e) = accept(stream, &state, ui) {
+                                debug!("client closed: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => error!("error accept client: {}", e),
+                }
+            }
+        })
+    }
+}
+
+/// Tell viewers, which send their size first, from control clients.
+fn accept(
+    mut stream: UnixStream,
+    state: &Arc<RwLock<state::state::State>>,
+    ui: manager::Manager,
+) -> io::Result<()> {
+    // NOTE: the socket is only made private after binding, check who connected as well
+    let uid = peer_uid(&stream)?;
+    if uid != unsafe { libc::getuid() } {
+        warn!("refusing client of uid {}", uid);
+        return Ok(());
+    }
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let line = line.trim_end();
+
+    match parse_message(line) {
+        Some(Message::Size(width, height)) => serve(stream, reader, (width, height), state, ui),
+        _ => {
+            let lines = iter::once(Ok(line.to_string())).chain(reader.lines());
+            control::serve(lines, &mut stream, state, &ui.actions())
+        }
+    }
+}
+
+fn serve(
+    mut stream: UnixStream,
+    reader: BufReader<UnixStream>,
+    (width, height): (u16, u16),
+    state: &Arc<RwLock<state::state::State>>,
+    mut ui: manager::Manager,
+) -> io::Result<()> {
+    let rx = read_messages(reader);
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    info!("viewer attached");
+
+    // NOTE: frames are drawn in memory under the state lock and written to the socket after it,
+    // so a viewer that stops reading cannot hold the lock
+    let buffer = Buffer::default();
+    let backend = CrosstermBackend::new(buffer.clone());
+    let mut terminal = Terminal::with_options(
+        backend,
+        TerminalOptions {
+            viewport: Viewport::Fixed(Rect::new(0, 0, width, height)),
+        },
+    )?;
+    terminal.clear()?;
+    ui.catch_up(&state.read().unwrap());
+
+    let result = frames(&mut stream, &buffer, &mut terminal, &rx, state, &mut ui);
+    match &result {
+        Ok(()) => info!("viewer detached"),
+        Err(e) => warn!("dropping viewer: {}", e),
+    }
+    // NOTE: the reader thread holds a clone, dropping ours would not close the socket
+    stream.shutdown(Shutdown::Both)?;
+    result
+}
+
+/// Draw and send frames until the viewer detaches or boda exits.
+fn frames(
+    stream: &mut UnixStream,
+    buffer: &Buffer,
+    terminal: &mut Terminal<CrosstermBackend<Buffer>>,
+    rx: &Receiver<Message>,
+    state: &Arc<RwLock<state::state::State>>,
+    ui: &mut manager::Manager,
+) -> io::Result<()> {
+    'frames: loop {
+        // NOTE: keys are handled without the lock, actions wait for the state manager's write lock
+        loop {
+            match rx.try_recv() {
+                Ok(Message::Size(width, height)) => {
+                    terminal.resize(Rect::new(0, 0, width, height))?;
+                }
+                Ok(Message::Key(key)) => ui.on_key(key),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => break 'frames,
+            }
+        }
+        if ui.is_detached() {
+            break;
+        }
+
+        {
+            let state = state.read().unwrap();
+            if !state.global.running {
+                break;
+            }
+            ui.update(terminal, &state)?;
+        }
+        stream.write_all(&buffer.take())?;
+        thread::sleep(FRAME);
+    }
+    Ok(())
+}
+
+/// Uid of the process on the other end of `stream`.
+fn peer_uid(stream: &UnixStream) -> io::Result<u32> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut cred as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+fn read_messages(reader: BufReader<UnixStream>) -> Receiver<Message> {
+    let (tx, rx) = unbounded();
+    thread::spawn(move || {
+        for line in reader.lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            match parse_message(&line) {
+                Some(message) => {
+                    if tx.send(message).is_err() {
+                        break;
+                    }
+                }
+                None => debug!("unknown viewer message: {}", line),
+            }
+        }
+    });
+    rx
+}
+
+fn parse_message(line: &str) -> Option<Message> {
+    let mut words = line.splitn(3, ' ');
+    match words.next()? {
+        "size" => Some(Message::Size(
+            words.next()?.parse().ok()?,
+            words.next()?.parse().ok()?,
+        )),
+        "key" => {
+            let modifiers = KeyModifiers::from_bits(words.next()?.parse().ok()?)?;
+            Some(Message::Key(KeyEvent::new(
+                decode_key(words.next()?)?,
+                modifiers,
+            )))
+        }
+        _ => None,
+    }
+}
+
+fn encode_key(code: KeyCode) -> Option<String> {
+    let name = match code {
+        KeyCode::Char(c) => return Some(format!("char:{}", c)),
+        KeyCode::Enter => "enter",
+        KeyCode::Esc => "esc",
+        KeyCode::Backspace => "backspace",
+        KeyCode::Tab => "tab",
+        KeyCode::BackTab => "backtab",
+        KeyCode::Home => "home",
+        KeyCode::End => "end",
+        KeyCode::PageUp => "pageup",
+        KeyCode::PageDown => "pagedown",
+        KeyCode::Up => "up",
+        KeyCode::Down => "down",
+        KeyCode::Left => "left",
+        KeyCode::Right => "right",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+fn decode_key(input: &str) -> Option<KeyCode> {
+    if let Some(c) = input.strip_prefix("char:") {
+        return c.chars().next().map(KeyCode::Char);
+    }
+    let code = match input {
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "backspace" => KeyCode::Backspace,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Sessions with a socket, newest first.
+fn sessions() -> Vec<String> {
+    let mut sessions = fs::read_dir(env::temp_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| {
+                    let name = entry.ok()?.file_name().into_string().ok()?;
+                    Some(name.strip_prefix(PREFIX)?.strip_suffix(SUFFIX)?.to_string())
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    // NOTE: sessions are ULIDs, sorting by name sorts by start time
+    sessions.sort_unstable_by(|a, b| b.cmp(a));
+    sessions
+}
+
+/// Connect to the newest running session starting with `session`, or the newest one when unset.
+pub(super) fn connect(session: Option<&str>) -> BodaResult<(String, UnixStream)> {
+    let candidates = sessions()
+        .into_iter()
+        .filter(|candidate| session.is_none_or(|session| candidate.starts_with(session)));
+    for candidate in candidates {
+        let path = socket_path(&candidate);
+        match UnixStream::connect(&path) {
+            Ok(stream) => return Ok((candidate, stream)),
+            Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => {
+                // NOTE: left behind by a boda that did not exit cleanly
+                debug!("removing stale socket {:?}", path);
+                let _ = fs::remove_file(&path);
+            }
+            Err(e) => debug!("cannot connect to {:?}: {}", path, e),
+        }
+    }
+
+    Err(match session {
+        Some(session) => format!("no running session matches {}", session),
+        None => "no running session".to_string(),
+    }
+    .into())
+}
+
+/// `boda attach`: show a running session in this terminal until it is detached or exits.
+pub fn attach(session: Option<&str>) -> BodaResult<()> {
+    let (session, stream) = connect(session)?;
+    let mut input = stream.try_clone()?;
+    let (tx, rx) = unbounded::<()>();
+    let mut output = stream;
+    thread::spawn(move || {
+        let mut stdout = io::stdout();
+        if let Err(e) = io::copy(&mut output, &mut stdout) {
+            debug!("error read session: {}", e);
+        }
+        let _ = tx.send(());
+    });
+
+    let terminal = ratatui::init();
+    let result = (|| -> BodaResult<()> {
+        let size = terminal.size()?;
+        writeln!(input, "size {} {}", size.width, size.height)?;
+        while rx.is_empty() {
+            if !event::poll(FRAME)? {
+                continue;
+            }
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    if let Some(code) = encode_key(key.code) {
+                        writeln!(input, "key {} {}", key.modifiers.bits(), code)?;
+                    }
+                }
+                Event::Resize(width, height) => {
+                    writeln!(input, "size {} {}", width, height)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    })();
+    ratatui::restore();
+
+    // NOTE: a write fails once the session has closed the socket, which is a normal exit
+    if let Err(e) = result {
+        debug!("error attach: {}", e);
+    }
+    println!("[detached from {}]", session);
+    Ok(())
+}
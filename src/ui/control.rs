@@ -0,0 +1,165 @@
+use std::{
+    io::{self, Read, Write},
+    net::Shutdown,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use log::{debug, info};
+
+use crate::{error::BodaResult, state, util};
+
+use super::attach;
+
+/// A command sent to the session socket, one per line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Request {
+    Pause,
+    Resume,
+    /// Run a 1-based watch, or all of them, now.
+    RunNow(Option<usize>),
+    SetInterval(Duration, Option<usize>),
+    Quit,
+    /// Latest output of every watch, as shown on screen.
+    Snapshot,
+}
+
+impl Request {
+    pub fn parse(input: &str) -> Result<Request, String> {
+        let mut words = input.split_whitespace();
+        let request = match words.next().unwrap_or("") {
+            "pause" => Request::Pause,
+            "resume" => Request::Resume,
+            "run-now" => Request::RunNow(words.next().map(parse_watch).transpose()?),
+            "set-interval" => {
+                let interval = words
+                    .next()
+                    .ok_or_else(|| "missing interval".to_string())
+                    .and_then(util::chrono::parse_duration)?;
+                Request::SetInterval(interval, words.next().map(parse_watch).transpose()?)
+            }
+            "quit" => Request::Quit,
+            "snapshot" => Request::Snapshot,
+            "" => return Err("empty command".to_string()),
+            command => {
+                return Err(format!(
+                    "unknown command: {command}, expected pause, resume, run-now [watch], set-interval <interval> [watch], quit or snapshot"
+                ));
+            }
+        };
+
+        match words.next() {
+            Some(extra) => Err(format!("unexpected argument: {extra}")),
+            None => Ok(request),
+        }
+    }
+}
+
+fn parse_watch(input: &str) -> Result<usize, String> {
+    match input.parse::<usize>() {
+        Ok(0) | Err(_) => Err(format!("expected a 1-based watch number: {input}")),
+        Ok(watch) => Ok(watch),
+    }
+}
+
+/// Answer requests from a control client until it closes its side.
+pub(super) fn serve(
+    lines: impl Iterator<Item = io::Result<String>>,
+    out: &mut impl Write,
+    state: &Arc<RwLock<state::state::State>>,
+    action_tx: &crossbeam_channel::Sender<state::action::Ui>,
+) -> io::Result<()> {
+    for line in lines {
+        let line = line?;
+        debug!("control request: {}", line);
+        match Request::parse(&line).and_then(|request| handle(request, state, action_tx)) {
+            Ok(reply) => out.write_all(reply.as_bytes())?,
+            Err(e) => writeln!(out, "error: {}", e)?,
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+fn handle(
+    request: Request,
+    state: &Arc<RwLock<state::state::State>>,
+    action_tx: &crossbeam_channel::Sender<state::action::Ui>,
+) -> Result<String, String> {
+    info!("control request: {:?}", request);
+    let watches = state.read().unwrap().global.watches.len();
+    let targets = |watch: Option<usize>| match watch {
+        Some(watch) if watch > watches => Err(format!("no watch {watch}, there are {watches}")),
+        Some(watch) => Ok(vec![watch - 1]),
+        None => Ok((0..watches).collect()),
+    };
+
+    let actions = match request {
+        Request::Pause => vec![state::action::Ui::SetPaused(true)],
+        Request::Resume => vec![state::action::Ui::SetPaused(false)],
+        Request::RunNow(watch) => targets(watch)?
+            .into_iter()
+            .map(|watch| state::action::Ui::RunNow(Some(watch)))
+            .collect(),
+        Request::SetInterval(interval, watch) => targets(watch)?
+            .into_iter()
+            .map(|watch| state::action::Ui::SetInterval(watch, interval))
+            .collect(),
+        Request::Quit => vec![state::action::Ui::Quit],
+        Request::Snapshot => return Ok(snapshot(&state.read().unwrap())),
+    };
+    for action in actions {
+        action_tx.send(action).map_err(|e| e.to_string())?;
+    }
+    Ok("ok\n".to_string())
+}
+
+fn snapshot(state: &state::state::State) -> String {
+    let mut out = String::new();
+    for (watch, w) in state.global.watches.iter().enumerate() {
+        let latest = state
+            .global
+            .get_history(watch)
+            .iter()
+            .find(|summary| summary.status.is_some())
+            .map(|summary| state::state::TargetCommand::Target(summary.id));
+        let result =
+            latest.and_then(|target| state.global.get_target_command_result(watch, &target));
+
+        match result {
+            Some(result) => {
+                out.push_str(&format!(
+                    "==> {} (#{} at {}, status {}) <==\n",
+                    w.command.join(" "),
+                    result.id,
+                    result.start.format("%H:%M:%S"),
+                    result.status.unwrap_or_default(),
+                ));
+                for line in result.get_content(&state.global.encoding, false) {
+                    out.push_str(&line);
+                    out.push('\n');
+                }
+            }
+            None => out.push_str(&format!("==> {} (no run yet) <==\n", w.command.join(" "))),
+        }
+    }
+    out
+}
+
+/// `boda send`: send one command to a running session and print the reply.
+pub fn send(session: Option<&str>, command: &str) -> BodaResult<()> {
+    Request::parse(command)?;
+    let (_, mut stream) = attach::connect(session)?;
+    writeln!(stream, "{}", command)?;
+    stream.shutdown(Shutdown::Write)?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    match reply.strip_prefix("error: ") {
+        Some(e) => Err(e.trim_end().to_string().into()),
+        None => {
+            print!("{}", reply);
+            Ok(())
+        }
+    }
+}
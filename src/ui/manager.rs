@@ -1,36 +1,114 @@
 use std::{
+    collections::VecDeque,
+    io::{self, Write},
     sync::{Arc, RwLock},
     thread,
     time::Duration,
 };
 
-use crossbeam_channel::{bounded, select, tick};
+use crossbeam_channel::{select, tick, unbounded};
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, poll};
 use log::{debug, error};
 use ratatui::{
-    DefaultTerminal, Frame,
-    layout::{Constraint, Layout, Margin},
+    DefaultTerminal, Frame, Terminal,
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Margin, Rect},
     style::{Color, Style, Stylize},
+    symbols::Marker,
     text::{Line, Span, Text},
-    widgets::{Block, Paragraph, Wrap},
+    widgets::{
+        Axis, Block, Chart, Dataset, GraphType, Paragraph, Sparkline, SparklineBar, Tabs, Wrap,
+    },
 };
 
 use crate::{error::BodaResult, state, util};
 
+// NOTE: four rows of bars plus the border
+const CHART_HEIGHT: u16 = 6;
+const FLASH_DURATION: Duration = Duration::from_millis(500);
+
 #[derive(Debug)]
 pub struct Manager {
     action_tx: crossbeam_channel::Sender<state::action::Ui>,
+
+    prompt: Option<Prompt>,
+    /// `state.ui.bells` when the bell last rang.
+    bells: u64,
+    osc: Osc,
+    /// `seq` of the last notification sent.
+    notified: u64,
+    /// Viewer of `boda attach`, quitting detaches instead of stopping boda.
+    attached: bool,
+    detached: bool,
+}
+
+/// Terminal escape sequence for desktop notifications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Osc {
+    #[value(name = "9")]
+    Osc9,
+    #[value(name = "777")]
+    Osc777,
+}
+
+#[derive(Debug, Default)]
+struct Prompt {
+    input: String,
+    error: Option<String>,
 }
 
 impl Manager {
-    pub fn new() -> (Manager, crossbeam_channel::Receiver<state::action::Ui>) {
-        let (tx, rx) = bounded::<state::action::Ui>(1);
+    pub fn new(osc: Osc) -> (Manager, crossbeam_channel::Receiver<state::action::Ui>) {
+        // NOTE: viewers and control clients send here too, a full channel would block a sender holding the read lock
+        let (tx, rx) = unbounded::<state::action::Ui>();
 
-        (Manager { action_tx: tx }, rx)
+        (
+            Manager {
+                action_tx: tx,
+                prompt: None,
+                bells: 0,
+                osc,
+                notified: 0,
+                attached: false,
+                detached: false,
+            },
+            rx,
+        )
     }
 }
 
 impl Manager {
+    /// A viewer for `boda attach`, sending its actions to the same state.
+    pub fn attached(&self) -> Manager {
+        Manager {
+            action_tx: self.action_tx.clone(),
+            prompt: None,
+            bells: 0,
+            osc: self.osc,
+            notified: 0,
+            attached: true,
+            detached: false,
+        }
+    }
+
+    /// Skip bells and notifications raised before the viewer attached.
+    pub(super) fn catch_up(&mut self, state: &state::state::State) {
+        self.bells = state.ui.bells;
+        self.notified = state
+            .ui
+            .notifications
+            .back()
+            .map_or(0, |notification| notification.seq);
+    }
+
+    pub(super) fn is_detached(&self) -> bool {
+        self.detached
+    }
+
+    pub(super) fn actions(&self) -> crossbeam_channel::Sender<state::action::Ui> {
+        self.action_tx.clone()
+    }
+
     pub fn run(mut self, state: Arc<RwLock<state::state::State>>) -> thread::JoinHandle<()> {
         thread::spawn(move || {
             let mut terminal = setup_terminal();
@@ -39,13 +117,15 @@ impl Manager {
             loop {
                 select! {
                     recv(ticker) -> _ => {
-                        let state = state.read().unwrap();
-                        if !state.global.running {
-                            cleanup_terminal();
-                            break;
-                        }
+                        {
+                            let state = state.read().unwrap();
+                            if !state.global.running {
+                                cleanup_terminal();
+                                break;
+                            }
 
-                        terminal.draw(|frame| self.render(frame, &state)).unwrap();
+                            self.update(&mut terminal, &state).unwrap();
+                        }
                         if poll(Duration::from_secs(0)).unwrap() {
                             self.handle_crossterm_events().unwrap()
                         }
@@ -55,21 +135,83 @@ impl Manager {
         })
     }
 
-    fn handle_crossterm_events(&self) -> BodaResult<()> {
-        match event::read()? {
-            Event::Key(key) if key.kind == KeyEventKind::Press => self.on_key_event(key),
-            _ => {}
+    /// Draw a frame, then ring the bell and send the notifications raised since the last one.
+    pub(super) fn update<W: Write>(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<W>>,
+        state: &state::state::State,
+    ) -> io::Result<()> {
+        terminal.draw(|frame| self.render(frame, state))?;
+        if state.ui.bells != self.bells {
+            self.bells = state.ui.bells;
+            ring_bell(terminal.backend_mut());
+        }
+        for notification in &state.ui.notifications {
+            if notification.seq > self.notified {
+                self.notified = notification.seq;
+                notify(terminal.backend_mut(), self.osc, notification);
+            }
         }
         Ok(())
     }
 
-    fn on_key_event(&self, key: KeyEvent) {
+    fn handle_crossterm_events(&mut self) -> BodaResult<()> {
+        if let Event::Key(key) = event::read()? {
+            self.on_key(key);
+        }
+        Ok(())
+    }
+
+    pub(super) fn on_key(&mut self, key: KeyEvent) {
+        match key.kind {
+            KeyEventKind::Press if self.prompt.is_some() => self.on_prompt_key_event(key),
+            KeyEventKind::Press => self.on_key_event(key),
+            _ => {}
+        }
+    }
+
+    fn on_prompt_key_event(&mut self, key: KeyEvent) {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+
+        match (key.modifiers, key.code) {
+            (_, KeyCode::Esc)
+            | (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => {
+                self.prompt = None;
+            }
+            (_, KeyCode::Enter) => match state::action::GoTo::parse(&prompt.input) {
+                Ok(goto) => {
+                    self.action_tx.send(state::action::Ui::GoTo(goto)).unwrap();
+                    self.prompt = None;
+                }
+                Err(e) => prompt.error = Some(e),
+            },
+            (_, KeyCode::Backspace) => {
+                prompt.input.pop();
+                prompt.error = None;
+            }
+            (_, KeyCode::Char(c)) => {
+                prompt.input.push(c);
+                prompt.error = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn on_key_event(&mut self, key: KeyEvent) {
         debug!("key event");
         match (key.modifiers, key.code) {
+            (_, KeyCode::Esc | KeyCode::Char('q'))
+            | (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C'))
+                if self.attached =>
+            {
+                self.detached = true;
+            }
             (_, KeyCode::Esc | KeyCode::Char('q'))
             | (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => {
                 debug!("sending quit");
-                if let Err(_) = self.action_tx.send(state::action::Ui::Quit) {
+                if self.action_tx.send(state::action::Ui::Quit).is_err() {
                     error!("error on send")
                 }
                 debug!("sent quit");
@@ -106,6 +248,95 @@ impl Manager {
             (_, KeyCode::Char('n')) => {
                 self.action_tx.send(state::action::Ui::SelectNext).unwrap();
             }
+            (_, KeyCode::Char('P')) => {
+                self.action_tx
+                    .send(state::action::Ui::SelectPrevChanged)
+                    .unwrap();
+            }
+            (_, KeyCode::Char('N')) => {
+                self.action_tx
+                    .send(state::action::Ui::SelectNextChanged)
+                    .unwrap();
+            }
+            (_, KeyCode::Char('g')) => {
+                self.action_tx
+                    .send(state::action::Ui::ToggleGroupHistory)
+                    .unwrap();
+            }
+            (_, KeyCode::Char('o')) => {
+                self.action_tx
+                    .send(state::action::Ui::ToggleExpandGroup)
+                    .unwrap();
+            }
+            (_, KeyCode::Char(':')) => {
+                self.prompt = Some(Prompt::default());
+            }
+            (_, KeyCode::Home) => {
+                self.action_tx.send(state::action::Ui::SelectFirst).unwrap();
+            }
+            (_, KeyCode::End) => {
+                self.action_tx
+                    .send(state::action::Ui::SelectLatest)
+                    .unwrap();
+            }
+            (_, KeyCode::PageDown) => {
+                self.action_tx
+                    .send(state::action::Ui::SelectPageDown)
+                    .unwrap();
+            }
+            (_, KeyCode::PageUp) => {
+                self.action_tx
+                    .send(state::action::Ui::SelectPageUp)
+                    .unwrap();
+            }
+            (_, KeyCode::Tab) => {
+                self.action_tx.send(state::action::Ui::FocusNext).unwrap();
+            }
+            (_, KeyCode::BackTab) => {
+                self.action_tx.send(state::action::Ui::FocusPrev).unwrap();
+            }
+            (_, KeyCode::Char(c @ '1'..='9')) => {
+                let watch = c as usize - '1' as usize;
+                self.action_tx
+                    .send(state::action::Ui::Focus(watch))
+                    .unwrap();
+            }
+            (_, KeyCode::Char('i')) => {
+                self.action_tx
+                    .send(state::action::Ui::ToggleShowDetails)
+                    .unwrap();
+            }
+            (_, KeyCode::Char('c')) => {
+                self.action_tx
+                    .send(state::action::Ui::ToggleShowChart)
+                    .unwrap();
+            }
+            (_, KeyCode::Char('m')) => {
+                self.action_tx
+                    .send(state::action::Ui::ToggleShowMetrics)
+                    .unwrap();
+            }
+            (_, KeyCode::Char('b')) => {
+                self.action_tx
+                    .send(state::action::Ui::ToggleSilence)
+                    .unwrap();
+            }
+            (_, KeyCode::Char('s')) => {
+                self.action_tx.send(state::action::Ui::TogglePause).unwrap();
+            }
+            (_, KeyCode::Char('r')) => {
+                self.action_tx
+                    .send(state::action::Ui::RunNow(None))
+                    .unwrap();
+            }
+            (_, KeyCode::Char('x')) => {
+                self.action_tx.send(state::action::Ui::ToggleHex).unwrap();
+            }
+            (_, KeyCode::Char('t')) => {
+                self.action_tx
+                    .send(state::action::Ui::ToggleLayout)
+                    .unwrap();
+            }
             (_, KeyCode::Char('l')) => {
                 self.action_tx
                     .send(state::action::Ui::SelectLatest)
@@ -129,9 +360,29 @@ k: Scroll Up
 Ctrl+d: Half-page Down
 Ctrl+u: Half-page Up
 <Space>: Show History
+i: Show run details
+c: Show duration chart
+m: Show metric charts
 p: Show previous
 n: Show next
-l: Show latest",
+P: Show previous change
+N: Show next change
+l: Show latest
+<PageDown>: Show a page older
+<PageUp>: Show a page newer
+<Home>: Show oldest
+<End>: Show latest
+:: Go to run number, time (14:32) or relative time (-15m)
+g: Group unchanged runs
+o: Expand/collapse group
+<Tab>: Focus next command
+<S-Tab>: Focus previous command
+1-9: Focus command
+t: Toggle tiles/tabs
+x: Toggle hex dump
+b: Silence beep/flash
+s: Pause/resume runs
+r: Run now",
                 ),
                 frame.area(),
             );
@@ -139,12 +390,102 @@ l: Show latest",
             return;
         }
 
+        let watches = state.global.watches.len();
+        let show_tabs = watches > 1 && state.ui.layout == state::state::Layout::Tabs;
+
+        let area = frame.area();
+        let rows = Layout::vertical([
+            Constraint::Length(if show_tabs { 1 } else { 0 }),
+            Constraint::Percentage(100),
+            Constraint::Length(if self.prompt.is_some() { 1 } else { 0 }),
+        ])
+        .split(area);
+
+        if show_tabs {
+            frame.render_widget(
+                Tabs::new(
+                    state
+                        .global
+                        .watches
+                        .iter()
+                        .map(|watch| watch.command.join(" ")),
+                )
+                .style(Style::new().gray())
+                .highlight_style(Style::new().reversed())
+                .select(state.ui.focus),
+                rows[0],
+            );
+        }
+
+        if watches == 1 || show_tabs {
+            self.render_watch(frame, rows[1], state, state.ui.focus, false);
+        } else {
+            let columns = (watches as f64).sqrt().ceil() as usize;
+            let tile_rows = watches.div_ceil(columns);
+            let row_chunks =
+                Layout::vertical(vec![Constraint::Ratio(1, tile_rows as u32); tile_rows])
+                    .split(rows[1]);
+
+            for (row, row_area) in row_chunks.iter().enumerate() {
+                let tiles = (watches - row * columns).min(columns);
+                let tile_chunks =
+                    Layout::horizontal(vec![Constraint::Ratio(1, tiles as u32); tiles])
+                        .split(*row_area);
+
+                for (column, tile_area) in tile_chunks.iter().enumerate() {
+                    let watch = row * columns + column;
+                    self.render_watch(frame, *tile_area, state, watch, watch == state.ui.focus);
+                }
+            }
+        }
+
+        if let Some(prompt) = &self.prompt {
+            let mut spans = vec![
+                Span::styled("Go to: ", Style::default().fg(Color::Gray)),
+                Span::raw(prompt.input.clone()),
+            ];
+            if let Some(error) = &prompt.error {
+                spans.push(Span::styled(
+                    format!("  {}", error),
+                    Style::default().fg(Color::Red),
+                ));
+            }
+            frame.render_widget(Paragraph::new(Line::from(spans)), rows[2]);
+        }
+    }
+
+    fn render_watch(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        state: &state::state::State,
+        watch: usize,
+        highlight: bool,
+    ) {
+        let view = &state.ui.views[watch];
         let result = state
             .global
-            .get_target_command_result(&state.ui.target_command);
-        let show_history = state.ui.show_history;
+            .get_target_command_result(watch, &view.target_command);
+        let show_history = state.ui.show_history && watch == state.ui.focus;
+        let details = if state.ui.show_details && watch == state.ui.focus {
+            state
+                .global
+                .get_target_summary(watch, &view.target_command)
+                .map(details_lines)
+        } else {
+            None
+        };
+        let flashing = view
+            .flashed_at
+            .is_some_and(|flashed_at| flashed_at.elapsed() < FLASH_DURATION);
+        let border_style = if flashing {
+            Style::new().yellow().bold()
+        } else if highlight {
+            Style::new().cyan()
+        } else {
+            Style::new().gray()
+        };
 
-        let area = frame.area();
         let rows =
             Layout::vertical([Constraint::Length(3), Constraint::Percentage(100)]).split(area);
 
@@ -155,27 +496,55 @@ l: Show latest",
         ])
         .split(rows[0]);
 
-        let layout = if show_history {
-            vec![Constraint::Percentage(80), Constraint::Percentage(20)]
-        } else {
-            vec![Constraint::Percentage(100)]
-        };
-        let content_chunks = Layout::horizontal(layout).split(rows[1]);
+        let show_metrics = state.ui.show_metrics && !state.global.metrics.is_empty();
+        let content_chunks = Layout::horizontal([
+            Constraint::Min(0),
+            Constraint::Percentage(if show_metrics { 40 } else { 0 }),
+            Constraint::Percentage(if show_history { 20 } else { 0 }),
+        ])
+        .split(rows[1]);
+        let show_chart = state.ui.show_chart && watch == state.ui.focus;
+        let output_chunks = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(if show_chart { CHART_HEIGHT } else { 0 }),
+            Constraint::Length(details.as_ref().map_or(0, |lines| lines.len() as u16 + 2)),
+        ])
+        .split(content_chunks[0]);
 
         frame.render_widget(
-            Paragraph::new(util::chrono::format_duration(state.global.interval)).block(
+            Paragraph::new(
+                match (
+                    state.global.watch_paths.is_empty(),
+                    state.global.watches[watch].timer,
+                ) {
+                    (true, _) => {
+                        util::chrono::format_duration(state.global.watches[watch].interval)
+                    }
+                    (false, true) => format!(
+                        "{} or change",
+                        util::chrono::format_duration(state.global.watches[watch].interval)
+                    ),
+                    (false, false) => "change".to_string(),
+                },
+            )
+            .block(
                 Block::bordered()
-                    .border_style(Style::new().gray())
-                    .title("Every")
+                    .border_style(border_style)
+                    .title(match (state.command.paused, state.ui.silenced) {
+                        (true, true) => "Every (paused, muted)",
+                        (true, false) => "Every (paused)",
+                        (false, true) => "Every (muted)",
+                        (false, false) => "Every",
+                    })
                     .title_style(Style::new().gray()),
             ),
             heading_chunks[0],
         );
         frame.render_widget(
-            Paragraph::new(state.global.command.join(" ")).block(
+            Paragraph::new(state.global.watches[watch].command.join(" ")).block(
                 Block::bordered()
-                    .border_style(Style::new().gray())
-                    .title("Command")
+                    .border_style(border_style)
+                    .title(format!("Command ({})", state.global.runner.label()))
                     .title_style(Style::new().gray()),
             ),
             heading_chunks[1],
@@ -187,8 +556,14 @@ l: Show latest",
             })
             .block(
                 Block::bordered()
-                    .border_style(Style::new().gray())
-                    .title("Timestamp")
+                    .border_style(border_style)
+                    .title(
+                        if view.hex || result.as_ref().is_some_and(|r| r.is_binary()) {
+                            "Timestamp (hex)"
+                        } else {
+                            "Timestamp"
+                        },
+                    )
                     .title_style(Style::new().gray()),
             ),
             heading_chunks[2],
@@ -198,7 +573,7 @@ l: Show latest",
             let (content, style) = (
                 Text::from(
                     result
-                        .get_content()
+                        .get_content(&state.global.encoding, view.hex)
                         .iter()
                         .map(|line| Line::from(line.clone()))
                         .collect::<Vec<Line>>(),
@@ -214,73 +589,402 @@ l: Show latest",
                 Paragraph::new(content)
                     .style(style)
                     .wrap(Wrap { trim: false })
-                    .scroll((state.ui.vertical_scroll, 0)),
-                content_chunks[0].inner(Margin {
+                    .scroll((view.vertical_scroll, 0)),
+                output_chunks[0].inner(Margin {
                     horizontal: 1,
                     vertical: 0,
                 }),
             );
         }
 
+        if let Some(details) = details {
+            frame.render_widget(
+                Paragraph::new(Text::from(details)).block(
+                    Block::bordered()
+                        .border_style(Style::new().gray())
+                        .title("Details")
+                        .title_style(Style::new().gray()),
+                ),
+                output_chunks[2],
+            );
+        }
+
+        if show_chart {
+            let width = output_chunks[1].width.saturating_sub(2) as usize;
+            let history = state.global.get_history(watch);
+            let failed = history
+                .iter()
+                .filter(|summary| summary.status.is_some_and(|status| status != 0))
+                .count();
+            frame.render_widget(
+                Sparkline::default().data(chart_bars(history, width)).block(
+                    Block::bordered()
+                        .border_style(Style::new().gray())
+                        .title(format!("Duration ({} of {} failed)", failed, history.len()))
+                        .title_style(Style::new().gray()),
+                ),
+                output_chunks[1],
+            );
+        }
+
+        if show_metrics {
+            self.render_metrics(frame, content_chunks[1], state, watch);
+        }
+
         if show_history {
             frame.render_widget(
                 Block::bordered().border_style(Style::new().gray()),
-                content_chunks[1],
+                content_chunks[2],
             );
 
-            let history = state.global.get_history();
-            let lines = history
+            let history = state.global.get_history(watch);
+            let rows = state.history_rows();
+            let target_position = state.target_position();
+            let lines = rows
                 .iter()
-                .map(|summary| {
-                    let mut lines = vec![];
-
-                    lines.push(Span::styled(
-                        format!("{}", summary.start.time()),
-                        if state.ui.target_command.is_target(summary) {
-                            Style::default().bg(Color::DarkGray)
-                        } else {
-                            Style::default()
-                        },
-                    ));
-
-                    lines.push(Span::raw(" "));
-                    let status = match summary.status {
-                        Some(0) => ("0".to_string(), Style::default().fg(Color::Green)),
-                        Some(s) => (format!("{}", s), Style::default().fg(Color::Red)),
-                        None => ("Running".to_string(), Style::default().fg(Color::Gray)),
-                    };
-                    lines.push(Span::styled(status.0, status.1));
-
-                    if let Some(end) = summary.end {
-                        lines.push(Span::raw(" "));
-                        lines.push(Span::styled(
-                            util::chrono::format_duration((end - summary.start).to_std().unwrap()),
-                            Style::default().fg(Color::Gray),
-                        ))
+                .map(|row| {
+                    let is_target = target_position.is_some_and(|position| row.contains(position));
+                    match *row {
+                        state::history::Row::Run { index, grouped } => {
+                            history_line(&history[index], is_target, grouped)
+                        }
+                        state::history::Row::Group { newest, oldest } => group_line(
+                            &history[newest],
+                            &history[oldest],
+                            oldest - newest + 1,
+                            is_target,
+                        ),
                     }
-
-                    Line::from(lines)
                 })
                 .collect::<Vec<Line>>();
             let text = Text::from(lines);
 
-            let scroll_offset = match state.ui.target_command {
-                state::state::TargetCommand::Latest => 0,
-                state::state::TargetCommand::Target(id) => {
-                    let height = content_chunks[1].height.saturating_sub(2); // Margin 고려
-                    (history.len() as u16 - id).saturating_sub(height / 2)
+            let scroll_offset = match target_position {
+                None => 0,
+                Some(position) => {
+                    let height = content_chunks[2].height.saturating_sub(2); // Margin 고려
+                    let row = state::history::row_position(&rows, position).unwrap_or(0) as u16;
+                    row.saturating_sub(height / 2)
                 }
             };
 
             frame.render_widget(
                 Paragraph::new(text).scroll((scroll_offset, 0)),
-                content_chunks[1].inner(Margin {
+                content_chunks[2].inner(Margin {
                     horizontal: 1,
                     vertical: 1,
                 }),
             );
         }
     }
+
+    /// One line chart per `--metric` rule, stacked, over the runs kept in history.
+    fn render_metrics(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        state: &state::state::State,
+        watch: usize,
+    ) {
+        let rules = &state.global.metrics;
+        let history = state.global.get_history(watch);
+        let chunks = Layout::vertical(vec![Constraint::Ratio(1, rules.len() as u32); rules.len()])
+            .split(area);
+
+        for (index, rule) in rules.iter().enumerate() {
+            let points = history
+                .iter()
+                .rev()
+                .filter_map(|summary| {
+                    let value = (*summary.metrics.get(index)?)?;
+                    Some((summary.start.timestamp_millis() as f64 / 1000.0, value))
+                })
+                .collect::<Vec<(f64, f64)>>();
+            let latest = points
+                .last()
+                .map_or("-".to_string(), |(_, value)| format!("{}", value));
+            let block = Block::bordered()
+                .border_style(Style::new().gray())
+                .title(format!("{}: {}", rule.name, latest))
+                .title_style(Style::new().gray());
+
+            let (Some(first), Some(last)) = (points.first(), points.last()) else {
+                frame.render_widget(block, chunks[index]);
+                continue;
+            };
+            let (min, max) = points
+                .iter()
+                .fold((f64::MAX, f64::MIN), |(min, max), (_, value)| {
+                    (min.min(*value), max.max(*value))
+                });
+            // NOTE: pad flat series so the line is not drawn on the border
+            let (min, max) = if min == max {
+                (min - 1.0, max + 1.0)
+            } else {
+                (min, max)
+            };
+            let time = |x: f64| {
+                chrono::DateTime::from_timestamp_millis((x * 1000.0) as i64)
+                    .map(|t| {
+                        t.with_timezone(&chrono::Local)
+                            .format("%H:%M:%S")
+                            .to_string()
+                    })
+                    .unwrap_or_default()
+            };
+
+            frame.render_widget(
+                Chart::new(vec![
+                    Dataset::default()
+                        .data(&points)
+                        .graph_type(GraphType::Line)
+                        .marker(Marker::Braille)
+                        .style(Style::default().fg(Color::Cyan)),
+                ])
+                .block(block)
+                .x_axis(
+                    Axis::default()
+                        .bounds([first.0, last.0.max(first.0 + 1.0)])
+                        .labels([time(first.0), time(last.0)])
+                        .style(Style::default().fg(Color::DarkGray)),
+                )
+                .y_axis(
+                    Axis::default()
+                        .bounds([min, max])
+                        .labels([format!("{}", min), format!("{}", max)])
+                        .style(Style::default().fg(Color::DarkGray)),
+                ),
+                chunks[index],
+            );
+        }
+    }
+}
+
+fn ring_bell(out: &mut impl Write) {
+    if let Err(e) = out.write_all(b"\x07").and_then(|_| out.flush()) {
+        error!("error ring bell: {}", e);
+    }
+}
+
+fn notify(out: &mut impl Write, osc: Osc, notification: &state::state::Notification) {
+    // NOTE: control characters would end the sequence early, `;` splits OSC 777 fields
+    let clean = |text: &str| {
+        text.chars()
+            .filter(|c| !c.is_control())
+            .map(|c| {
+                if c == ';' && osc == Osc::Osc777 {
+                    ','
+                } else {
+                    c
+                }
+            })
+            .collect::<String>()
+    };
+    let sequence = match osc {
+        Osc::Osc9 => format!(
+            "\x1b]9;{}: {}\x07",
+            clean(&notification.title),
+            clean(&notification.body)
+        ),
+        Osc::Osc777 => format!(
+            "\x1b]777;notify;{};{}\x07",
+            clean(&notification.title),
+            clean(&notification.body)
+        ),
+    };
+
+    if let Err(e) = out.write_all(sequence.as_bytes()).and_then(|_| out.flush()) {
+        error!("error send notification: {}", e);
+    }
+}
+
+fn target_style(is_target: bool) -> Style {
+    if is_target {
+        Style::default().bg(Color::DarkGray)
+    } else {
+        Style::default()
+    }
+}
+
+fn history_line(
+    summary: &state::state::CommandResultSummary,
+    is_target: bool,
+    grouped: bool,
+) -> Line<'static> {
+    let mut lines = vec![];
+
+    if grouped {
+        lines.push(Span::raw("  "));
+    }
+    lines.push(Span::styled(
+        format!("{}", summary.start.time()),
+        target_style(is_target),
+    ));
+
+    lines.push(Span::raw(" "));
+    let status = match summary.status {
+        Some(0) => ("0".to_string(), Style::default().fg(Color::Green)),
+        Some(s) => (format!("{}", s), Style::default().fg(Color::Red)),
+        None => ("Running".to_string(), Style::default().fg(Color::Gray)),
+    };
+    lines.push(Span::styled(status.0, status.1));
+
+    if let Some(end) = summary.end {
+        lines.push(Span::raw(" "));
+        lines.push(Span::styled(
+            util::chrono::format_duration((end - summary.start).to_std().unwrap()),
+            Style::default().fg(Color::Gray),
+        ))
+    }
+
+    if summary.changed == Some(false) {
+        lines.push(Span::styled(" =", Style::default().fg(Color::DarkGray)));
+    }
+
+    if summary.truncated {
+        lines.push(Span::styled(" ✂", Style::default().fg(Color::Yellow)));
+    }
+
+    if let Some(usage) = summary.usage {
+        lines.push(Span::styled(
+            format!(
+                " {} {}",
+                util::chrono::format_duration(usage.user_time + usage.system_time),
+                util::size::format_size(usage.max_rss)
+            ),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+
+    Line::from(lines)
+}
+
+/// One bar per run for the last `width` runs, oldest first, colored by status.
+fn chart_bars(
+    history: &VecDeque<state::state::CommandResultSummary>,
+    width: usize,
+) -> Vec<SparklineBar> {
+    history
+        .iter()
+        .take(width)
+        .rev()
+        .map(|summary| match (summary.end, summary.status) {
+            (Some(end), Some(status)) => {
+                let duration = (end - summary.start).num_milliseconds().max(1) as u64;
+                let color = if status == 0 {
+                    Color::Green
+                } else {
+                    Color::Red
+                };
+                SparklineBar::from(duration).style(Style::default().fg(color))
+            }
+            _ => SparklineBar::from(None),
+        })
+        .collect()
+}
+
+fn details_lines(summary: &state::state::CommandResultSummary) -> Vec<Line<'static>> {
+    let field = |name: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{:<9}", name), Style::default().fg(Color::Gray)),
+            Span::raw(value),
+        ])
+    };
+
+    let time = |t: &util::chrono::DateTime| t.format("%Y-%m-%d %H:%M:%S%.3f").to_string();
+    let output = |size: Option<u64>, lines: Option<u64>| match (size, lines) {
+        (Some(size), Some(lines)) => format!("{}, {} lines", util::size::format_size(size), lines),
+        _ => "-".to_string(),
+    };
+
+    let mut lines = vec![
+        field("Run", format!("#{}", summary.id)),
+        field("Start", time(&summary.start)),
+    ];
+    match summary.end {
+        Some(end) => {
+            lines.push(field("End", time(&end)));
+            lines.push(field(
+                "Duration",
+                util::chrono::format_duration((end - summary.start).to_std().unwrap_or_default()),
+            ));
+        }
+        None => lines.push(field("End", "Running".to_string())),
+    }
+    lines.push(field(
+        "Status",
+        match (summary.status, summary.signal) {
+            (Some(status), Some(signal)) => format!("{} (killed by signal {})", status, signal),
+            (Some(status), None) => status.to_string(),
+            (None, _) => "-".to_string(),
+        },
+    ));
+    lines.push(field(
+        "Stdout",
+        output(summary.stdout_size, summary.stdout_lines),
+    ));
+    lines.push(field(
+        "Stderr",
+        output(summary.stderr_size, summary.stderr_lines),
+    ));
+    lines.push(field(
+        "Changed",
+        match summary.changed {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "-",
+        }
+        .to_string(),
+    ));
+    if summary.truncated {
+        lines.push(field("Output", "truncated by --max-output".to_string()));
+    }
+    match summary.usage {
+        Some(usage) => {
+            lines.push(field(
+                "CPU",
+                format!(
+                    "{} user, {} system",
+                    util::chrono::format_duration(usage.user_time),
+                    util::chrono::format_duration(usage.system_time)
+                ),
+            ));
+            lines.push(field("Max RSS", util::size::format_size(usage.max_rss)));
+            lines.push(field(
+                "I/O",
+                format!(
+                    "{} read, {} written",
+                    util::size::format_size(usage.read_bytes),
+                    util::size::format_size(usage.write_bytes)
+                ),
+            ));
+        }
+        None if summary.status.is_none() => {}
+        None => lines.push(field("Usage", "Unavailable".to_string())),
+    }
+    lines
+}
+
+fn group_line(
+    newest: &state::state::CommandResultSummary,
+    oldest: &state::state::CommandResultSummary,
+    count: usize,
+    is_target: bool,
+) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(
+            format!(
+                "{}–{}",
+                oldest.start.format("%H:%M:%S"),
+                newest.start.format("%H:%M:%S")
+            ),
+            target_style(is_target),
+        ),
+        Span::styled(
+            format!(" ×{} unchanged", count),
+            Style::default().fg(Color::DarkGray),
+        ),
+    ])
 }
 
 fn cleanup_terminal() {
@@ -289,7 +993,5 @@ fn cleanup_terminal() {
 
 fn setup_terminal() -> DefaultTerminal {
     color_eyre::install().expect("unable to install color_eyre");
-    let terminal = ratatui::init();
-
-    return terminal;
+    ratatui::init()
 }
@@ -1 +1,3 @@
+pub mod attach;
+pub mod control;
 pub mod manager;
@@ -9,3 +9,41 @@ pub fn format_duration(duration: Duration) -> String {
         format!("{:.3}s", duration.as_secs_f64())
     }
 }
+
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let mut rest = input.trim();
+    if rest.is_empty() {
+        return Err("empty duration".to_string());
+    }
+
+    let mut duration = Duration::ZERO;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let (value, tail) = rest.split_at(split);
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+
+        let value = value
+            .parse::<f64>()
+            .map_err(|_| format!("invalid duration: {input}"))?;
+        let secs = match unit {
+            "ms" => value / 1000.0,
+            "" | "s" => value,
+            "m" => value * 60.0,
+            "h" => value * 60.0 * 60.0,
+            "d" => value * 60.0 * 60.0 * 24.0,
+            unit => return Err(format!("unknown duration unit: {unit}")),
+        };
+        duration = Duration::try_from_secs_f64(secs)
+            .ok()
+            .and_then(|secs| duration.checked_add(secs))
+            .ok_or_else(|| format!("duration out of range: {input}"))?;
+        rest = tail;
+    }
+
+    Ok(duration)
+}
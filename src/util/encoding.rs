@@ -0,0 +1,106 @@
+use std::fmt::Write;
+
+// NOTE: only the head of the output is inspected to tell binary from text
+const SNIFF_LEN: usize = 8192;
+const HEX_WIDTH: usize = 16;
+
+/// Text encoding used to decode stored output for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Latin1,
+    Other(&'static encoding_rs::Encoding),
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding::Other(encoding_rs::UTF_8)
+    }
+}
+
+impl Encoding {
+    /// Accepts `latin-1` plus any WHATWG label, e.g. `utf-8`, `euc-kr`, `shift_jis`.
+    pub fn parse(label: &str) -> Result<Encoding, String> {
+        let label = label.trim().to_ascii_lowercase();
+        match label.as_str() {
+            "latin-1" | "latin1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            "shift-jis" => Ok(Encoding::Other(encoding_rs::SHIFT_JIS)),
+            _ => encoding_rs::Encoding::for_label(label.as_bytes())
+                .map(Encoding::Other)
+                .ok_or_else(|| format!("unknown encoding: {label}")),
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Latin1 => bytes.iter().map(|b| *b as char).collect(),
+            Encoding::Other(encoding) => encoding.decode_without_bom_handling(bytes).0.into_owned(),
+        }
+    }
+
+    /// Number of lines `decode(bytes).lines()` yields, without decoding when newlines are plain bytes.
+    pub fn count_lines(&self, bytes: &[u8]) -> usize {
+        match self {
+            Encoding::Other(encoding) if !encoding.is_ascii_compatible() => {
+                self.decode(bytes).lines().count()
+            }
+            _ => {
+                let newlines = bytes.iter().filter(|b| **b == b'\n').count();
+                match bytes.last() {
+                    Some(b'\n') | None => newlines,
+                    Some(_) => newlines + 1,
+                }
+            }
+        }
+    }
+}
+
+/// Whether `bytes` looks like binary data rather than text in any supported encoding.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    let head = &bytes[..bytes.len().min(SNIFF_LEN)];
+    if head.contains(&0) {
+        return true;
+    }
+
+    let control = head
+        .iter()
+        .filter(|b| {
+            b.is_ascii_control() && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b | 0x08)
+        })
+        .count();
+    control * 10 > head.len()
+}
+
+/// Number of lines `hex_dump` yields for `len` bytes.
+pub fn hex_dump_len(len: usize) -> usize {
+    len.div_ceil(HEX_WIDTH)
+}
+
+/// `xxd`-style lines: offset, hex bytes and printable ASCII.
+pub fn hex_dump(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .chunks(HEX_WIDTH)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let mut line = format!("{:08x}  ", index * HEX_WIDTH);
+            for column in 0..HEX_WIDTH {
+                match chunk.get(column) {
+                    Some(b) => write!(line, "{:02x} ", b).unwrap(),
+                    None => line.push_str("   "),
+                }
+                if column == HEX_WIDTH / 2 - 1 {
+                    line.push(' ');
+                }
+            }
+            line.push_str(" |");
+            line.extend(chunk.iter().map(|b| {
+                if b.is_ascii_graphic() || *b == b' ' {
+                    *b as char
+                } else {
+                    '.'
+                }
+            }));
+            line.push('|');
+            line
+        })
+        .collect()
+}
@@ -20,9 +20,9 @@ impl FileLogger {
             .open(path)
             .expect("cannot open log file");
 
-        return FileLogger {
+        FileLogger {
             file: Mutex::new(file),
-        };
+        }
     }
 
     pub fn log<T: std::fmt::Display>(&self, level: log::Level, line: T) {
@@ -1,2 +1,4 @@
 pub mod chrono;
+pub mod encoding;
 pub mod log;
+pub mod size;